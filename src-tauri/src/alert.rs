@@ -1,84 +1,103 @@
 use anyhow::Error;
-use log::error;
-use serde_json::json;
-use tauri::{AppHandle, Manager, Runtime};
+use log::{error, info, warn};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Instant;
+use tauri::{AppHandle, Manager, Runtime, WindowEvent};
 
-use crate::SettingsManagerState;
+use crate::alert_queue::{AlertQueue, Offer, PendingAlert};
+use crate::error_catalog::{ErrorCode, Severity};
+use crate::tracking;
 #[cfg(target_os = "macos")]
 use tauri::ActivationPolicy;
-use tauri_plugin_aptabase::EventTracker;
 use urlencoding::encode;
 
-const WINDOW_LABEL: &str = "alert";
+pub(crate) const WINDOW_LABEL: &str = "alert";
+
+static ALERTS: Mutex<AlertQueue> = Mutex::new(AlertQueue::new());
 
 pub trait Alert {
-    fn alert(&self, title: &str, message: &str, error: Option<anyhow::Error>, silence: bool) -> ();
+    fn alert(&self, code: ErrorCode, error: Option<anyhow::Error>, silence: bool) -> () {
+        self.alert_with_detail(code, None, error, silence)
+    }
+
+    /// Like `alert`, with details for the user, e.g. the reason of a license error.
+    fn alert_with_detail(
+        &self,
+        code: ErrorCode,
+        detail: Option<String>,
+        error: Option<anyhow::Error>,
+        silence: bool,
+    ) -> ();
 }
 
 impl Alert for AppHandle {
-    fn alert(&self, title: &str, message: &str, error: Option<anyhow::Error>, silence: bool) -> () {
-        let allow_sending_error = self
-            .state::<SettingsManagerState>()
-            .get_settings()
-            .map(|s| s.user.allow_tracking)
-            .unwrap_or(false);
-
-        if let Some(e) = error {
-            error!(
-                "error '{}' with message '{}', error: {:?}",
-                title, message, e
-            );
-            if allow_sending_error {
-                self.track_event(
-                    title,
-                    Some(json!({
-                        "info": message.to_string(),
-                        "error": e.to_string(),
-                    })),
-                );
-            }
-        } else {
-            error!("error '{}' with message '{}'", title, message);
-            if allow_sending_error {
-                self.track_event(
-                    title,
-                    Some(json!({
-                        "info": format!("{}", message)
-                    })),
-                );
+    fn alert_with_detail(
+        &self,
+        code: ErrorCode,
+        detail: Option<String>,
+        error: Option<anyhow::Error>,
+        silence: bool,
+    ) -> () {
+        let severity = code.severity();
+        match (&error, severity) {
+            (Some(e), Severity::Warning) => {
+                warn!("{} {:?} ({:?}), error: {:?}", code.code(), code, detail, e)
             }
+            (None, Severity::Warning) => warn!("{} {:?} ({:?})", code.code(), code, detail),
+            (Some(e), _) => error!("{} {:?} ({:?}), error: {:?}", code.code(), code, detail, e),
+            (None, _) => error!("{} {:?} ({:?})", code.code(), code, detail),
         }
+        tracking::track(
+            self,
+            tracking::Event::Alert {
+                code,
+                error: error.map(|e| e.to_string()),
+            },
+        );
 
-        if !silence {
-            match display_alert(self.app_handle(), title, message) {
-                Ok(_) => {}
-                Err(e) => {
-                    error!("unable to display error {:?}", e);
-                    self.exit(-1);
-                }
-            }
+        if silence {
+            return;
+        }
+        // the lock is released before the window is shown, which may alert again
+        let offer = alerts().offer(code, detail, Instant::now());
+        match offer {
+            Offer::Show(alert) => display_alert(self, alert),
+            Offer::Queued => info!("alert {} queued", code.code()),
+            Offer::Duplicate => info!("alert {} is shown already", code.code()),
+            Offer::RateLimited => warn!("alert {} raised too often, not shown", code.code()),
         }
     }
 }
 
-fn show_alert<R: Runtime>(
-    app: &AppHandle<R>,
-    title: String,
-    message: String,
-) -> Result<(), anyhow::Error> {
-    if let Some(window) = app.get_webview_window(WINDOW_LABEL) {
-        window.close()?;
-        return Ok(());
+fn alerts() -> MutexGuard<'static, AlertQueue> {
+    ALERTS.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Shows the next queued alert, after the current one is gone.
+fn alert_dismissed<R: Runtime>(app: &AppHandle<R>) {
+    let next = alerts().dismissed(Instant::now());
+    if let Some(alert) = next {
+        display_alert(app, alert);
     }
+}
 
-    let _window = tauri::WebviewWindowBuilder::new(
+fn show_alert<R: Runtime>(app: &AppHandle<R>, alert: &PendingAlert) -> Result<(), anyhow::Error> {
+    if app.get_webview_window(WINDOW_LABEL).is_some() {
+        return Err(anyhow::anyhow!("alert window is shown already"));
+    }
+
+    let window = tauri::WebviewWindowBuilder::new(
         app,
         WINDOW_LABEL,
         tauri::WebviewUrl::App(
             format!(
-                "/alert?title={title}&message={message}",
-                title = encode(&title),
-                message = encode(&message)
+                "/alert?code={code}&severity={severity:?}&title={title}&message={message}&detail={detail}&count={count}",
+                code = encode(alert.code.code()),
+                severity = alert.code.severity(),
+                title = encode(alert.code.title()),
+                message = encode(alert.code.message()),
+                detail = encode(alert.detail.as_deref().unwrap_or_default()),
+                count = alert.count,
             )
             .into(),
         ),
@@ -94,15 +113,24 @@ fn show_alert<R: Runtime>(
     .visible(false)
     .build()?;
 
+    let app = app.clone();
+    window.on_window_event(move |event| {
+        if let WindowEvent::Destroyed = event {
+            alert_dismissed(&app);
+        }
+    });
+
     Ok(())
 }
 
+/// Logs errors of the UI. Free text is passed as detail, the user facing text
+/// comes from the catalog.
 #[specta::specta]
 #[tauri::command]
-pub fn alert_log_client_error(app: AppHandle, title: String, message: String, error: String) {
-    app.app_handle().alert(
-        title.as_str(),
-        message.as_str(),
+pub fn alert_log_client_error(app: AppHandle, code: ErrorCode, detail: String, error: String) {
+    app.app_handle().alert_with_detail(
+        code,
+        Some(detail).filter(|detail| !detail.is_empty()),
         Some(anyhow::Error::msg(error)),
         false,
     );
@@ -116,7 +144,26 @@ pub fn close_error_window(window: tauri::Window) {
         .expect("alert window must exists and should never be closed");
 }
 
-fn display_alert<R>(app: &AppHandle<R>, title: &str, message: &str) -> Result<(), Error>
+fn display_alert<R>(app: &AppHandle<R>, alert: PendingAlert)
+where
+    R: Runtime,
+{
+    if let Err(e) = try_display_alert(app, &alert) {
+        error!(
+            "unable to display alert {}, use native dialog: {:?}",
+            alert.code.code(),
+            e
+        );
+        let app = app.clone();
+        std::thread::spawn(move || {
+            show_native_dialog(&alert)
+                .unwrap_or_else(|e| error!("unable to display native dialog: {:?}", e));
+            alert_dismissed(&app);
+        });
+    }
+}
+
+fn try_display_alert<R>(app: &AppHandle<R>, alert: &PendingAlert) -> Result<(), Error>
 where
     R: Runtime,
 {
@@ -124,7 +171,76 @@ where
     app.app_handle()
         .set_activation_policy(ActivationPolicy::Regular)?;
 
-    show_alert(app, title.to_string(), message.to_string())?;
+    show_alert(app, alert)?;
 
     Ok(())
 }
+
+fn native_dialog_text(alert: &PendingAlert) -> String {
+    match &alert.detail {
+        Some(detail) => format!(
+            "{}\n\n{}\n\n({})",
+            alert.code.message(),
+            detail,
+            alert.code.code()
+        ),
+        None => format!("{}\n\n({})", alert.code.message(), alert.code.code()),
+    }
+}
+
+/// Blocks until the user closed the dialog.
+#[cfg(target_os = "windows")]
+fn show_native_dialog(alert: &PendingAlert) -> Result<(), Error> {
+    use std::os::windows::ffi::OsStrExt;
+    use winapi::um::winuser::{MessageBoxW, MB_ICONERROR, MB_OK, MB_TOPMOST};
+
+    let wide = |text: &str| {
+        std::ffi::OsStr::new(text)
+            .encode_wide()
+            .chain(Some(0))
+            .collect::<Vec<u16>>()
+    };
+    let title = wide(alert.code.title());
+    let text = wide(&native_dialog_text(alert));
+    let result = unsafe {
+        MessageBoxW(
+            std::ptr::null_mut(),
+            text.as_ptr(),
+            title.as_ptr(),
+            MB_OK | MB_ICONERROR | MB_TOPMOST,
+        )
+    };
+    if result == 0 {
+        return Err(anyhow::anyhow!(std::io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+/// Blocks until the user closed the dialog.
+#[cfg(target_os = "macos")]
+fn show_native_dialog(alert: &PendingAlert) -> Result<(), Error> {
+    let status = std::process::Command::new("osascript")
+        .arg("-e")
+        .arg("on run argv\ndisplay alert (item 1 of argv) message (item 2 of argv) as critical\nend run")
+        .arg(alert.code.title())
+        .arg(native_dialog_text(alert))
+        .status()?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("osascript failed with {}", status));
+    }
+    Ok(())
+}
+
+/// Blocks until the user closed the dialog.
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn show_native_dialog(alert: &PendingAlert) -> Result<(), Error> {
+    let status = std::process::Command::new("zenity")
+        .arg("--error")
+        .arg(format!("--title={}", alert.code.title()))
+        .arg(format!("--text={}", native_dialog_text(alert)))
+        .status()?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("zenity failed with {}", status));
+    }
+    Ok(())
+}
@@ -0,0 +1,145 @@
+use crate::error_catalog::{ErrorCode, Severity};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// An error code isn't shown more often than this within the rate limit window.
+const MAX_ALERTS_PER_CODE: usize = 3;
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PendingAlert {
+    pub code: ErrorCode,
+    pub detail: Option<String>,
+    /// How often the identical alert was raised, until it was dismissed.
+    pub count: u32,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Offer {
+    /// Nothing is shown right now, show the alert.
+    Show(PendingAlert),
+    /// The alert is shown, after the current one is dismissed.
+    Queued,
+    /// The identical alert is shown or queued already.
+    Duplicate,
+    /// The error code was shown too often recently.
+    RateLimited,
+}
+
+/// Keeps the alerts in order, so an alert raised while another one is shown
+/// isn't lost.
+pub(crate) struct AlertQueue {
+    showing: Option<PendingAlert>,
+    pending: VecDeque<PendingAlert>,
+    shown: Vec<(ErrorCode, Instant)>,
+}
+
+impl AlertQueue {
+    pub const fn new() -> Self {
+        AlertQueue {
+            showing: None,
+            pending: VecDeque::new(),
+            shown: Vec::new(),
+        }
+    }
+
+    pub fn offer(&mut self, code: ErrorCode, detail: Option<String>, now: Instant) -> Offer {
+        if let Some(identical) = self
+            .showing
+            .iter_mut()
+            .chain(self.pending.iter_mut())
+            .find(|alert| alert.code == code && alert.detail == detail)
+        {
+            identical.count += 1;
+            return Offer::Duplicate;
+        }
+
+        self.shown
+            .retain(|(_, shown_at)| now.duration_since(*shown_at) < RATE_LIMIT_WINDOW);
+        let shown = self
+            .shown
+            .iter()
+            .filter(|(shown, _)| *shown == code)
+            .count();
+        let queued = self
+            .pending
+            .iter()
+            .filter(|alert| alert.code == code)
+            .count();
+        if code.severity() != Severity::Critical && shown + queued >= MAX_ALERTS_PER_CODE {
+            return Offer::RateLimited;
+        }
+
+        let alert = PendingAlert {
+            code,
+            detail,
+            count: 1,
+        };
+        if self.showing.is_some() {
+            self.pending.push_back(alert);
+            return Offer::Queued;
+        }
+        self.show(alert.clone(), now);
+        Offer::Show(alert)
+    }
+
+    /// The current alert was dismissed, returns the next one to show.
+    pub fn dismissed(&mut self, now: Instant) -> Option<PendingAlert> {
+        self.showing = None;
+        let next = self.pending.pop_front()?;
+        self.show(next.clone(), now);
+        Some(next)
+    }
+
+    fn show(&mut self, alert: PendingAlert, now: Instant) {
+        self.shown.push((alert.code, now));
+        self.showing = Some(alert);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alerts_are_queued_deduplicated_and_rate_limited() {
+        let now = Instant::now();
+        let mut queue = AlertQueue::new();
+
+        assert!(matches!(
+            queue.offer(ErrorCode::SessionStartFailed, None, now),
+            Offer::Show(_)
+        ));
+        assert_eq!(
+            queue.offer(ErrorCode::SessionStartFailed, None, now),
+            Offer::Duplicate
+        );
+        assert_eq!(
+            queue.offer(ErrorCode::LicenseRejected, Some("expired".to_string()), now),
+            Offer::Queued
+        );
+
+        let next = queue.dismissed(now).unwrap();
+        assert_eq!(next.code, ErrorCode::LicenseRejected);
+        assert!(queue.dismissed(now).is_none());
+
+        assert!(matches!(
+            queue.offer(ErrorCode::SessionStartFailed, None, now),
+            Offer::Show(_)
+        ));
+        queue.dismissed(now);
+        assert!(matches!(
+            queue.offer(ErrorCode::SessionStartFailed, None, now),
+            Offer::Show(_)
+        ));
+        queue.dismissed(now);
+        assert_eq!(
+            queue.offer(ErrorCode::SessionStartFailed, None, now),
+            Offer::RateLimited
+        );
+        assert!(matches!(
+            queue.offer(ErrorCode::SessionStartFailed, None, now + RATE_LIMIT_WINDOW),
+            Offer::Show(_)
+        ));
+    }
+}
@@ -0,0 +1,253 @@
+use crate::app_config::{AnalyticsSinkKind, AppConfig};
+use crate::audit_log;
+use crate::http_client::HttpClient;
+use crate::tracking::TrackedEvent;
+use anyhow::Context;
+use async_trait::async_trait;
+use log::{info, warn};
+use serde_json::{json, Value};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_aptabase::EventTracker;
+
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+const FILE_NAME: &str = "analytics.jsonl";
+
+/// A destination for tracking events. Consent is checked before an event
+/// reaches a sink.
+#[async_trait]
+pub(crate) trait AnalyticsSink: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Sends the events in one go, if the backend supports it. Either all
+    /// events are accepted or the whole batch is retried later.
+    async fn send_batch(&self, events: &[TrackedEvent]) -> Result<(), anyhow::Error>;
+}
+
+/// Creates the configured sinks. Sinks, which can't be created, are skipped.
+pub(crate) fn from_config(
+    app: &AppHandle,
+    app_config: &AppConfig,
+    client: &HttpClient,
+) -> Vec<Box<dyn AnalyticsSink>> {
+    let mut sinks: Vec<Box<dyn AnalyticsSink>> = Vec::new();
+    for kind in app_config.get_analytics_sinks() {
+        match kind {
+            AnalyticsSinkKind::Mixpanel => sinks.push(Box::new(MixpanelSink {
+                client: client.clone(),
+                url: app_config.get_tracking_url(),
+                token: app_config.get_mixpanel_token(),
+            })),
+            AnalyticsSinkKind::Aptabase => sinks.push(Box::new(AptabaseSink {
+                app_handle: app.clone(),
+            })),
+            AnalyticsSinkKind::Http => match app_config.get_analytics_url() {
+                Some(url) => sinks.push(Box::new(HttpSink {
+                    client: client.clone(),
+                    url,
+                })),
+                None => warn!("http analytics sink needs an analytics url, skip it"),
+            },
+            AnalyticsSinkKind::File => match app.path().app_log_dir() {
+                Ok(dir) => sinks.push(Box::new(JsonlFileSink::new(dir.join(FILE_NAME)))),
+                Err(err) => warn!("no log dir for the file analytics sink: {:?}", err),
+            },
+            AnalyticsSinkKind::Noop => sinks.push(Box::new(NoopSink)),
+        }
+    }
+    info!(
+        "analytics sinks: {:?}",
+        sinks.iter().map(|sink| sink.name()).collect::<Vec<_>>()
+    );
+    sinks
+}
+
+pub(crate) struct MixpanelSink {
+    client: HttpClient,
+    url: String,
+    token: String,
+}
+
+#[async_trait]
+impl AnalyticsSink for MixpanelSink {
+    fn name(&self) -> &'static str {
+        AnalyticsSinkKind::Mixpanel.name()
+    }
+
+    async fn send_batch(&self, events: &[TrackedEvent]) -> Result<(), anyhow::Error> {
+        let event_data: Vec<Value> = events
+            .iter()
+            .map(|event| {
+                let mut properties = event.properties.clone();
+                properties.insert("token".to_string(), json!(self.token));
+                properties.insert("time".to_string(), json!(event.timestamp.timestamp()));
+                properties.insert("app_version".to_string(), json!(event.app_version));
+                properties.insert("platform".to_string(), json!(event.platform));
+                properties.insert("arch".to_string(), json!(event.arch));
+                properties.insert("distinct_id".to_string(), json!(event.distinct_id));
+                properties.insert("license_state".to_string(), json!(event.license_state));
+                json!({
+                    "event": event.name,
+                    "properties": properties,
+                })
+            })
+            .collect();
+
+        let res = self
+            .client
+            .send(
+                self.client
+                    .post(&self.url)
+                    .header("Accept", "text/plain")
+                    .header("Content-Type", "application/json")
+                    .json(&event_data)
+                    .timeout(SEND_TIMEOUT),
+            )
+            .await?;
+
+        let json = res.json::<Value>().await?;
+        let status = json
+            .get("status")
+            .map(|v| v.as_u64().unwrap_or(0))
+            .ok_or(anyhow::anyhow!("expected status from tracking request"))?;
+
+        if status == 1 {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("error sending tracking event: {}", json))
+        }
+    }
+}
+
+/// Hands the events to the Aptabase plugin, which batches them itself.
+pub(crate) struct AptabaseSink {
+    app_handle: AppHandle,
+}
+
+#[async_trait]
+impl AnalyticsSink for AptabaseSink {
+    fn name(&self) -> &'static str {
+        AnalyticsSinkKind::Aptabase.name()
+    }
+
+    async fn send_batch(&self, events: &[TrackedEvent]) -> Result<(), anyhow::Error> {
+        for event in events {
+            let mut properties = event.properties.clone();
+            properties.insert("license_state".to_string(), json!(event.license_state));
+            let properties = Value::Object(properties);
+            audit_log::record_json(
+                "aptabase",
+                &json!({"name": event.name, "props": properties}),
+            );
+            self.app_handle.track_event(&event.name, Some(properties));
+        }
+        Ok(())
+    }
+}
+
+/// Posts the events as JSON array to a self-hosted endpoint.
+pub(crate) struct HttpSink {
+    client: HttpClient,
+    url: String,
+}
+
+#[async_trait]
+impl AnalyticsSink for HttpSink {
+    fn name(&self) -> &'static str {
+        AnalyticsSinkKind::Http.name()
+    }
+
+    async fn send_batch(&self, events: &[TrackedEvent]) -> Result<(), anyhow::Error> {
+        self.client
+            .send(self.client.post(&self.url).json(events).timeout(SEND_TIMEOUT))
+            .await?;
+        Ok(())
+    }
+}
+
+/// Appends one JSON object per line, e.g. to inspect the events locally.
+pub(crate) struct JsonlFileSink {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl JsonlFileSink {
+    pub fn new(path: PathBuf) -> Self {
+        JsonlFileSink {
+            path,
+            lock: Mutex::new(()),
+        }
+    }
+}
+
+#[async_trait]
+impl AnalyticsSink for JsonlFileSink {
+    fn name(&self) -> &'static str {
+        AnalyticsSinkKind::File.name()
+    }
+
+    async fn send_batch(&self, events: &[TrackedEvent]) -> Result<(), anyhow::Error> {
+        let mut lines = String::new();
+        for event in events {
+            lines.push_str(&serde_json::to_string(event)?);
+            lines.push('\n');
+        }
+
+        let _guard = self
+            .lock
+            .lock()
+            .map_err(|err| anyhow::anyhow!(err.to_string()))?;
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(lines.as_bytes()))
+            .with_context(|| format!("unable to write analytics file {:?}", self.path))
+    }
+}
+
+pub(crate) struct NoopSink;
+
+#[async_trait]
+impl AnalyticsSink for NoopSink {
+    fn name(&self) -> &'static str {
+        AnalyticsSinkKind::Noop.name()
+    }
+
+    async fn send_batch(&self, _events: &[TrackedEvent]) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracking::Event;
+
+    #[test]
+    fn test_file_sink_appends_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let sink = JsonlFileSink::new(dir.path().join("logs").join(FILE_NAME));
+        let event = TrackedEvent::for_tests(Event::WelcomeQuit("payment".to_string()));
+
+        tauri::async_runtime::block_on(async {
+            sink.send_batch(std::slice::from_ref(&event)).await.unwrap();
+            sink.send_batch(&[event]).await.unwrap();
+        });
+
+        let content = std::fs::read_to_string(dir.path().join("logs").join(FILE_NAME)).unwrap();
+        let lines: Vec<Value> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["name"], "welcome_quit_payment");
+    }
+}
@@ -1,21 +1,405 @@
+use log::{info, warn};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tauri::Url;
+
+const DEFAULT_LICENSE_GRACE_PERIOD_DAYS: u32 = 7;
+const LICENSE_GRACE_PERIOD_ENV: &str = "JUSTDRINK_LICENSE_GRACE_DAYS";
+const HTTPS_PROXY_ENV: &str = "JUSTDRINK_HTTPS_PROXY";
+const CA_BUNDLE_ENV: &str = "JUSTDRINK_CA_BUNDLE";
+const DEFAULT_TRIAL_REMINDER_DAYS: [u32; 3] = [3, 1, 0];
+const TRIAL_REMINDER_DAYS_ENV: &str = "JUSTDRINK_TRIAL_REMINDER_DAYS";
+const DEFAULT_ANALYTICS_SINKS: [AnalyticsSinkKind; 2] =
+    [AnalyticsSinkKind::Mixpanel, AnalyticsSinkKind::Aptabase];
+const ANALYTICS_SINKS_ENV: &str = "JUSTDRINK_ANALYTICS_SINKS";
+const DEFAULT_MIXPANEL_TOKEN: &str = "21c5f5354133116affaafe40b4d316db";
+const MIXPANEL_TOKEN_ENV: &str = "JUSTDRINK_MIXPANEL_TOKEN";
+
+const PRODUCTION_API_URL: &str = "https://just-drink.app";
+pub(crate) const LOCAL_API_URL: &str = "http://just-drink.test:8080";
+/// Short for the local license server, e.g. `--api-url local`.
+const LOCAL_API_ALIAS: &str = "local";
+const DEFAULT_TRACKING_URL: &str = "https://api.mixpanel.com/track?ip=1&verbose=1";
+const ENDPOINTS_FILE_NAME: &str = "endpoints.json";
+
+const API_URL_ENV: &str = "JUSTDRINK_API_URL";
+const TRACKING_URL_ENV: &str = "JUSTDRINK_TRACKING_URL";
+const UPDATER_URL_ENV: &str = "JUSTDRINK_UPDATER_URL";
+const ANALYTICS_URL_ENV: &str = "JUSTDRINK_ANALYTICS_URL";
+const ENDPOINTS_FILE_ENV: &str = "JUSTDRINK_ENDPOINTS_FILE";
+
+const API_URL_ARG: &str = "--api-url";
+const TRACKING_URL_ARG: &str = "--tracking-url";
+const UPDATER_URL_ARG: &str = "--updater-url";
+const ANALYTICS_URL_ARG: &str = "--analytics-url";
+const ENDPOINTS_FILE_ARG: &str = "--endpoints-file";
+
+static ENDPOINTS: OnceLock<Endpoints> = OnceLock::new();
+
+/// Release builds keep the license defaults. The servers, the proxy and the CA
+/// bundle can be changed in every build, because an active license is only
+/// trusted with a signature of the compiled license key.
+const ALLOWS_LICENSE_OVERRIDES: bool = cfg!(debug_assertions);
+
+/// The servers the app talks to. QA can point them to a staging or mock
+/// server, without recompiling the app.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Endpoints {
+    api_url: String,
+    tracking_url: String,
+    /// Without an override, the endpoint of `tauri.conf.json` is used.
+    updater_url: Option<String>,
+    /// The self-hosted endpoint of the `http` analytics sink.
+    analytics_url: Option<String>,
+}
+
+/// Endpoint overrides of one source. Sources are, in ascending priority: the
+/// endpoints file, environment variables and command line flags.
+#[derive(Deserialize, Debug, Clone, Default)]
+struct EndpointOverrides {
+    #[serde(rename(deserialize = "apiUrl"), default)]
+    api_url: Option<String>,
+    #[serde(rename(deserialize = "trackingUrl"), default)]
+    tracking_url: Option<String>,
+    #[serde(rename(deserialize = "updaterUrl"), default)]
+    updater_url: Option<String>,
+    #[serde(rename(deserialize = "analyticsUrl"), default)]
+    analytics_url: Option<String>,
+}
+
+/// Where tracking events are sent to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnalyticsSinkKind {
+    Mixpanel,
+    Aptabase,
+    /// Posts the events as JSON to the self-hosted analytics url.
+    Http,
+    /// Appends the events to a JSONL file in the log directory.
+    File,
+    Noop,
+}
+
+impl AnalyticsSinkKind {
+    /// The name in `JUSTDRINK_ANALYTICS_SINKS`, which is also the name of the sink.
+    pub fn name(&self) -> &'static str {
+        match self {
+            AnalyticsSinkKind::Mixpanel => "mixpanel",
+            AnalyticsSinkKind::Aptabase => "aptabase",
+            AnalyticsSinkKind::Http => "http",
+            AnalyticsSinkKind::File => "file",
+            AnalyticsSinkKind::Noop => "noop",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "mixpanel" => Some(AnalyticsSinkKind::Mixpanel),
+            "aptabase" => Some(AnalyticsSinkKind::Aptabase),
+            "http" => Some(AnalyticsSinkKind::Http),
+            "file" => Some(AnalyticsSinkKind::File),
+            "none" | "noop" => Some(AnalyticsSinkKind::Noop),
+            _ => None,
+        }
+    }
+}
+
 pub struct AppConfig {
-    url: String,
+    endpoints: Endpoints,
+    license_grace_period_days: u32,
+    https_proxy: Option<String>,
+    ca_bundle: Option<PathBuf>,
+    trial_reminder_days: Vec<u32>,
+    analytics_sinks: Vec<AnalyticsSinkKind>,
+    route_analytics: bool,
+    mixpanel_token: String,
 }
 
 impl AppConfig {
+    /// Resolves the endpoints once. The endpoints file is looked up in the
+    /// config directory, unless another file is given.
+    pub fn init(config_dir: Option<PathBuf>) {
+        let endpoints = ENDPOINTS.get_or_init(|| Endpoints::resolve(config_dir));
+        if endpoints.is_custom() {
+            info!("use custom backend: {:?}", endpoints);
+        }
+    }
+
     pub fn build() -> Self {
-        if cfg!(feature = "local") {
-            AppConfig {
-                url: "http://just-drink.test:8080".to_string(),
+        AppConfig {
+            endpoints: ENDPOINTS.get_or_init(|| Endpoints::resolve(None)).clone(),
+            license_grace_period_days: Self::license_grace_period_days(),
+            https_proxy: Self::non_empty_env(HTTPS_PROXY_ENV),
+            ca_bundle: Self::non_empty_env(CA_BUNDLE_ENV).map(PathBuf::from),
+            trial_reminder_days: Self::trial_reminder_days(),
+            analytics_sinks: Self::analytics_sinks(),
+            route_analytics: Self::configured_analytics_sinks().is_none(),
+            mixpanel_token: Self::non_empty_env(MIXPANEL_TOKEN_ENV)
+                .unwrap_or_else(|| DEFAULT_MIXPANEL_TOKEN.to_string()),
+        }
+    }
+
+    pub fn get_url(&self) -> String {
+        self.endpoints.api_url.clone()
+    }
+
+    pub fn get_tracking_url(&self) -> String {
+        self.endpoints.tracking_url.clone()
+    }
+
+    pub fn get_updater_url(&self) -> Option<String> {
+        self.endpoints.updater_url.clone()
+    }
+
+    pub fn get_analytics_url(&self) -> Option<String> {
+        self.endpoints.analytics_url.clone()
+    }
+
+    /// True, if any endpoint differs from the production servers.
+    pub fn is_custom_backend(&self) -> bool {
+        self.endpoints.is_custom()
+    }
+
+    /// How long a verified license stays valid, while the license server is unreachable.
+    pub fn get_license_grace_period(&self) -> chrono::Duration {
+        chrono::Duration::days(self.license_grace_period_days.into())
+    }
+
+    /// Proxy for all requests of the app, e.g. `http://proxy.corp.example:3128`.
+    pub fn get_https_proxy(&self) -> Option<String> {
+        self.https_proxy.clone()
+    }
+
+    /// PEM bundle with additional root certificates, e.g. of a corporate TLS proxy.
+    pub fn get_ca_bundle(&self) -> Option<&Path> {
+        self.ca_bundle.as_deref()
+    }
+
+    /// Days before the end of the trial, at which the user gets reminded. `0`
+    /// reminds on the last day of the trial.
+    pub fn get_trial_reminder_days(&self) -> Vec<u32> {
+        self.trial_reminder_days.clone()
+    }
+
+    /// The sinks for tracking events, e.g. `JUSTDRINK_ANALYTICS_SINKS=file,http`.
+    /// It doesn't depend on the endpoints, so it can be read before setup.
+    pub fn analytics_sinks() -> Vec<AnalyticsSinkKind> {
+        Self::configured_analytics_sinks().unwrap_or_else(|| DEFAULT_ANALYTICS_SINKS.to_vec())
+    }
+
+    pub fn get_analytics_sinks(&self) -> Vec<AnalyticsSinkKind> {
+        self.analytics_sinks.clone()
+    }
+
+    /// True, if each event only goes to its default sink. Sinks listed in
+    /// `JUSTDRINK_ANALYTICS_SINKS` receive every event instead.
+    pub fn is_analytics_routed(&self) -> bool {
+        self.route_analytics
+    }
+
+    pub fn get_mixpanel_token(&self) -> String {
+        self.mixpanel_token.clone()
+    }
+
+    fn configured_analytics_sinks() -> Option<Vec<AnalyticsSinkKind>> {
+        let value = Self::non_empty_env(ANALYTICS_SINKS_ENV)?;
+        value
+            .split(',')
+            .map(|name| AnalyticsSinkKind::parse(name).ok_or(name))
+            .collect::<Result<Vec<_>, _>>()
+            .inspect_err(|name| {
+                warn!("invalid {ANALYTICS_SINKS_ENV} '{value}': unknown sink '{name}'");
+            })
+            .ok()
+    }
+
+    fn trial_reminder_days() -> Vec<u32> {
+        let Some(value) = Self::non_empty_env(TRIAL_REMINDER_DAYS_ENV) else {
+            return DEFAULT_TRIAL_REMINDER_DAYS.to_vec();
+        };
+        value
+            .split(',')
+            .map(|day| day.trim().parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap_or_else(|err| {
+                warn!("invalid {TRIAL_REMINDER_DAYS_ENV} '{value}': {err}");
+                DEFAULT_TRIAL_REMINDER_DAYS.to_vec()
+            })
+    }
+
+    fn non_empty_env(name: &str) -> Option<String> {
+        std::env::var(name)
+            .ok()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    }
+
+    fn license_grace_period_days() -> u32 {
+        if !ALLOWS_LICENSE_OVERRIDES {
+            return DEFAULT_LICENSE_GRACE_PERIOD_DAYS;
+        }
+        match std::env::var(LICENSE_GRACE_PERIOD_ENV) {
+            Ok(value) => value.parse::<u32>().unwrap_or_else(|err| {
+                warn!("invalid {LICENSE_GRACE_PERIOD_ENV} '{value}': {err}");
+                DEFAULT_LICENSE_GRACE_PERIOD_DAYS
+            }),
+            Err(_) => DEFAULT_LICENSE_GRACE_PERIOD_DAYS,
+        }
+    }
+}
+
+impl Endpoints {
+    fn resolve(config_dir: Option<PathBuf>) -> Self {
+        let args: Vec<String> = std::env::args().collect();
+        let file = Self::arg_value(&args, ENDPOINTS_FILE_ARG)
+            .or_else(|| AppConfig::non_empty_env(ENDPOINTS_FILE_ENV))
+            .map(PathBuf::from)
+            .or_else(|| config_dir.map(|dir| dir.join(ENDPOINTS_FILE_NAME)));
+
+        let mut sources = Vec::new();
+        if let Some(file) = file {
+            sources.push(Self::file_overrides(&file));
+        }
+        sources.push(EndpointOverrides {
+            api_url: AppConfig::non_empty_env(API_URL_ENV),
+            tracking_url: AppConfig::non_empty_env(TRACKING_URL_ENV),
+            updater_url: AppConfig::non_empty_env(UPDATER_URL_ENV),
+            analytics_url: AppConfig::non_empty_env(ANALYTICS_URL_ENV),
+        });
+        sources.push(Self::cli_overrides(&args));
+
+        Self::merge(&sources)
+    }
+
+    fn is_custom(&self) -> bool {
+        self.api_url != PRODUCTION_API_URL
+            || self.tracking_url != DEFAULT_TRACKING_URL
+            || self.updater_url.is_some()
+            || self.analytics_url.is_some()
+    }
+
+    /// Applies the sources in order, invalid urls are ignored.
+    fn merge(sources: &[EndpointOverrides]) -> Self {
+        let mut endpoints = Endpoints {
+            api_url: PRODUCTION_API_URL.to_string(),
+            tracking_url: DEFAULT_TRACKING_URL.to_string(),
+            updater_url: None,
+            analytics_url: None,
+        };
+        for source in sources {
+            let api_url = source.api_url.as_deref().map(|url| match url.trim() {
+                LOCAL_API_ALIAS => LOCAL_API_URL.to_string(),
+                url => url.to_string(),
+            });
+            if let Some(url) = Self::validated(&api_url, "api url") {
+                endpoints.api_url = url.trim_end_matches('/').to_string();
+            }
+            if let Some(url) = Self::validated(&source.tracking_url, "tracking url") {
+                endpoints.tracking_url = url;
             }
-        } else {
-            AppConfig {
-                url: "https://just-drink.app".to_string(),
+            if let Some(url) = Self::validated(&source.updater_url, "updater url") {
+                endpoints.updater_url = Some(url);
+            }
+            if let Some(url) = Self::validated(&source.analytics_url, "analytics url") {
+                endpoints.analytics_url = Some(url);
             }
         }
+        endpoints
     }
 
-    pub fn get_url(&self) -> String {
-        self.url.clone()
+    fn validated(url: &Option<String>, name: &str) -> Option<String> {
+        let url = url.as_ref()?.trim();
+        match Url::parse(url) {
+            Ok(parsed) if !["http", "https"].contains(&parsed.scheme()) => {
+                warn!("ignore {name} '{url}': only http and https are supported");
+                None
+            }
+            Ok(parsed) if parsed.host_str().is_none() => {
+                warn!("ignore {name} '{url}': host is missing");
+                None
+            }
+            Ok(_) => Some(url.to_string()),
+            Err(err) => {
+                warn!("ignore {name} '{url}': {err}");
+                None
+            }
+        }
+    }
+
+    fn file_overrides(path: &Path) -> EndpointOverrides {
+        match std::fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|err| {
+                warn!("ignore endpoints file {:?}: {}", path, err);
+                EndpointOverrides::default()
+            }),
+            Err(_) => EndpointOverrides::default(),
+        }
+    }
+
+    fn cli_overrides(args: &[String]) -> EndpointOverrides {
+        EndpointOverrides {
+            api_url: Self::arg_value(args, API_URL_ARG),
+            tracking_url: Self::arg_value(args, TRACKING_URL_ARG),
+            updater_url: Self::arg_value(args, UPDATER_URL_ARG),
+            analytics_url: Self::arg_value(args, ANALYTICS_URL_ARG),
+        }
+    }
+
+    /// Supports `--flag value` and `--flag=value`.
+    fn arg_value(args: &[String], flag: &str) -> Option<String> {
+        args.iter().enumerate().find_map(|(index, arg)| {
+            if arg == flag {
+                args.get(index + 1).cloned()
+            } else {
+                arg.strip_prefix(flag)
+                    .and_then(|rest| rest.strip_prefix('='))
+                    .map(|value| value.to_string())
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli_overrides_env_and_file() {
+        let args: Vec<String> = ["justdrink", "--quiet", "--api-url=http://localhost:8080/"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let file = EndpointOverrides {
+            api_url: Some("https://staging.just-drink.app".to_string()),
+            tracking_url: Some("https://staging.just-drink.app/track".to_string()),
+            updater_url: None,
+            analytics_url: None,
+        };
+        let env = EndpointOverrides {
+            updater_url: Some("ftp://just-drink.test/latest.json".to_string()),
+            ..EndpointOverrides::default()
+        };
+
+        let endpoints = Endpoints::merge(&[file, env, Endpoints::cli_overrides(&args)]);
+        assert_eq!(endpoints.api_url, "http://localhost:8080");
+        assert_eq!(
+            endpoints.tracking_url,
+            "https://staging.just-drink.app/track"
+        );
+        assert_eq!(endpoints.updater_url, None);
+        assert!(endpoints.is_custom());
+    }
+
+    #[test]
+    fn test_default_endpoints() {
+        let endpoints = Endpoints::merge(&[EndpointOverrides::default()]);
+        assert_eq!(endpoints.api_url, PRODUCTION_API_URL);
+        assert!(!endpoints.is_custom());
+
+        let local = EndpointOverrides {
+            api_url: Some(LOCAL_API_ALIAS.to_string()),
+            ..EndpointOverrides::default()
+        };
+        assert_eq!(Endpoints::merge(&[local]).api_url, LOCAL_API_URL);
     }
 }
@@ -0,0 +1,424 @@
+use anyhow::Context;
+use chrono::Utc;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use specta::Type;
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use tauri::Url;
+use tauri_plugin_http::reqwest::header::{
+    HeaderMap, AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE, COOKIE, PROXY_AUTHORIZATION,
+};
+use tauri_plugin_http::reqwest::Request;
+
+const FILE_NAME: &str = "privacy-audit.jsonl";
+const ROTATED_FILE_NAME: &str = "privacy-audit.1.jsonl";
+const MAX_FILE_SIZE: u64 = 256 * 1024;
+const MAX_VALUE_LENGTH: usize = 500;
+const REDACTED: &str = "[redacted]";
+const MAX_ENTRIES: usize = 500;
+
+static AUDIT_LOG: OnceLock<AuditLog> = OnceLock::new();
+
+/// One payload, which left the machine.
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+pub struct AuditEntry {
+    pub(crate) timestamp: String,
+    pub(crate) method: String,
+    /// The url with a redacted query.
+    pub(crate) destination: String,
+    /// The headers set by the app, e.g. the update channel.
+    #[serde(default)]
+    pub(crate) headers: BTreeMap<String, String>,
+    /// The body without secrets like license keys or email addresses.
+    pub(crate) body: String,
+}
+
+/// Records every outbound payload in a rotating file in the log directory, so
+/// users can see what we received from them.
+struct AuditLog {
+    dir: PathBuf,
+    lock: Mutex<()>,
+}
+
+pub fn init(dir: PathBuf) {
+    info!("privacy audit log in {:?}", dir);
+    AUDIT_LOG.get_or_init(|| AuditLog {
+        dir,
+        lock: Mutex::new(()),
+    });
+}
+
+/// Records a request of our http client.
+pub fn record_request(request: &Request) {
+    let content_type = request
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let body = request
+        .body()
+        .and_then(|body| body.as_bytes())
+        .map(|bytes| redact_body(content_type, bytes))
+        .unwrap_or_default();
+    record(
+        request.method().as_str(),
+        &redact_url(request.url()),
+        redact_headers(request.headers()),
+        body,
+    );
+}
+
+/// Records a payload, which is sent by a plugin instead of our http client.
+pub fn record_json(destination: &str, body: &Value) {
+    record(
+        "POST",
+        destination,
+        BTreeMap::new(),
+        redact_json(body.clone()).to_string(),
+    );
+}
+
+/// Records a request without body, which is sent by a plugin, e.g. the check
+/// for updates.
+pub fn record_headers(method: &str, destination: &str, headers: &HeaderMap) {
+    record(method, destination, redact_headers(headers), String::new());
+}
+
+/// The recorded payloads, newest first.
+pub fn entries(limit: usize) -> Result<Vec<AuditEntry>, anyhow::Error> {
+    let Some(audit_log) = AUDIT_LOG.get() else {
+        return Ok(Vec::new());
+    };
+    let _guard = audit_log
+        .lock
+        .lock()
+        .map_err(|err| anyhow::anyhow!(err.to_string()))?;
+
+    let mut entries = Vec::new();
+    for file_name in [ROTATED_FILE_NAME, FILE_NAME] {
+        let path = audit_log.dir.join(file_name);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err).with_context(|| format!("unable to read {:?}", path)),
+        };
+        entries.extend(
+            content
+                .lines()
+                .filter_map(|line| serde_json::from_str::<AuditEntry>(line).ok()),
+        );
+    }
+    entries.reverse();
+    entries.truncate(limit);
+    Ok(entries)
+}
+
+/// Everything we sent recently, for the "What we sent" view.
+#[specta::specta]
+#[tauri::command]
+pub fn privacy_audit_log() -> Result<Vec<AuditEntry>, String> {
+    entries(MAX_ENTRIES).map_err(|err| err.to_string())
+}
+
+fn record(method: &str, destination: &str, headers: BTreeMap<String, String>, body: String) {
+    let Some(audit_log) = AUDIT_LOG.get() else {
+        return;
+    };
+    let entry = AuditEntry {
+        timestamp: Utc::now().to_rfc3339(),
+        method: method.to_string(),
+        destination: destination.to_string(),
+        headers,
+        body,
+    };
+    audit_log
+        .append(&entry)
+        .unwrap_or_else(|err| warn!("unable to write privacy audit log: {:?}", err));
+}
+
+impl AuditLog {
+    fn append(&self, entry: &AuditEntry) -> Result<(), anyhow::Error> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+
+        let _guard = self
+            .lock
+            .lock()
+            .map_err(|err| anyhow::anyhow!(err.to_string()))?;
+        fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(FILE_NAME);
+        if fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0) > MAX_FILE_SIZE {
+            fs::rename(&path, self.dir.join(ROTATED_FILE_NAME))?;
+        }
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?
+            .write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+fn redact_url(url: &Url) -> String {
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(key, value)| {
+            let value = match redact_value(&key, Value::String(value.into_owned())) {
+                Value::String(value) => value,
+                other => other.to_string(),
+            };
+            (key.into_owned(), value)
+        })
+        .collect();
+    let mut url = url.clone();
+    if pairs.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    url.to_string()
+}
+
+/// The body describes the content itself, credentials are never shown.
+fn redact_headers(headers: &HeaderMap) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .filter(|(name, _)| ![CONTENT_TYPE, CONTENT_LENGTH].contains(name))
+        .map(|(name, value)| {
+            let value = if [AUTHORIZATION, PROXY_AUTHORIZATION, COOKIE].contains(name) {
+                REDACTED.to_string()
+            } else {
+                String::from_utf8_lossy(value.as_bytes()).into_owned()
+            };
+            (name.to_string(), value)
+        })
+        .collect()
+}
+
+fn redact_body(content_type: &str, bytes: &[u8]) -> String {
+    if content_type.starts_with("application/x-www-form-urlencoded") {
+        let pairs = String::from_utf8_lossy(bytes)
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                let key = urlencoding::decode(key).map(|key| key.into_owned()).unwrap_or_default();
+                let value = urlencoding::decode(&value.replace('+', " "))
+                    .map(|value| value.into_owned())
+                    .unwrap_or_default();
+                let value = redact_value(&key, Value::String(value));
+                (key, value)
+            })
+            .collect::<serde_json::Map<String, Value>>();
+        return Value::Object(pairs).to_string();
+    }
+    if content_type.starts_with("multipart/form-data") {
+        if let Some(form) = redact_multipart(content_type, bytes) {
+            return form.to_string();
+        }
+    }
+    match serde_json::from_slice::<Value>(bytes) {
+        Ok(json) => redact_json(json).to_string(),
+        Err(_) => format!("<{} bytes>", bytes.len()),
+    }
+}
+
+/// The redacted fields of the form and only name and size of the attachments.
+fn redact_multipart(content_type: &str, bytes: &[u8]) -> Option<Value> {
+    let boundary = content_type
+        .split(';')
+        .find_map(|param| param.trim().strip_prefix("boundary="))?
+        .trim_matches('"');
+    let delimiter = format!("--{}", boundary);
+
+    let mut fields = serde_json::Map::new();
+    let mut attachments = Vec::new();
+    for part in split_bytes(bytes, delimiter.as_bytes()).into_iter().skip(1) {
+        if part.starts_with(b"--") {
+            break;
+        }
+        let part = part.strip_prefix(b"\r\n").unwrap_or(part);
+        let part = part.strip_suffix(b"\r\n").unwrap_or(part);
+        let header_end = find_bytes(part, b"\r\n\r\n")?;
+        let headers = String::from_utf8_lossy(&part[..header_end]);
+        let content = &part[header_end + 4..];
+        let disposition = headers.lines().find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim()
+                .eq_ignore_ascii_case("content-disposition")
+                .then(|| value.to_string())
+        })?;
+        let name = disposition_param(&disposition, "name")?;
+        match disposition_param(&disposition, "filename") {
+            Some(file_name) => attachments.push(json!({
+                "name": name,
+                "fileName": file_name,
+                "size": content.len(),
+            })),
+            None => {
+                let value = serde_json::from_slice(content).unwrap_or_else(|_| {
+                    Value::String(String::from_utf8_lossy(content).into_owned())
+                });
+                let value = redact_value(&name, value);
+                fields.insert(name, value);
+            }
+        }
+    }
+    if !attachments.is_empty() {
+        fields.insert("attachments".to_string(), Value::Array(attachments));
+    }
+    Some(Value::Object(fields))
+}
+
+fn disposition_param(disposition: &str, key: &str) -> Option<String> {
+    disposition.split(';').find_map(|param| {
+        let value = param.trim().strip_prefix(key)?.strip_prefix('=')?;
+        Some(
+            value
+                .trim_matches('"')
+                .replace("\\\"", "\"")
+                .replace("\\\\", "\\"),
+        )
+    })
+}
+
+fn split_bytes<'a>(bytes: &'a [u8], delimiter: &[u8]) -> Vec<&'a [u8]> {
+    let mut parts = Vec::new();
+    let mut rest = bytes;
+    while let Some(index) = find_bytes(rest, delimiter) {
+        parts.push(&rest[..index]);
+        rest = &rest[index + delimiter.len()..];
+    }
+    parts.push(rest);
+    parts
+}
+
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+pub(crate) fn redact_json(json: Value) -> Value {
+    match json {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| {
+                    let value = redact_value(&key, value);
+                    (key, value)
+                })
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(values.into_iter().map(redact_json).collect()),
+        Value::String(text) if text.chars().count() > MAX_VALUE_LENGTH => {
+            Value::String(format!("{}…", text.chars().take(MAX_VALUE_LENGTH).collect::<String>()))
+        }
+        other => other,
+    }
+}
+
+fn redact_value(key: &str, value: Value) -> Value {
+    let Value::String(text) = &value else {
+        return redact_json(value);
+    };
+    match key.to_lowercase().replace('_', "").as_str() {
+        "email" => Value::String(mask_email(text)),
+        "licensekey" | "code" => Value::String(mask_all_but_last(text, 4)),
+        "token" | "password" => Value::String(REDACTED.to_string()),
+        _ => redact_json(value),
+    }
+}
+
+fn mask_email(email: &str) -> String {
+    match email.split_once('@') {
+        Some((name, domain)) => format!(
+            "{}***@{}",
+            name.chars().next().map(String::from).unwrap_or_default(),
+            domain
+        ),
+        None => REDACTED.to_string(),
+    }
+}
+
+fn mask_all_but_last(text: &str, visible: usize) -> String {
+    let count = text.chars().count();
+    if count <= visible {
+        return REDACTED.to_string();
+    }
+    format!("****{}", text.chars().skip(count - visible).collect::<String>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multipart::MultipartForm;
+
+    #[test]
+    fn test_redact_secrets() {
+        let body = json!({
+            "licenseKey": "JD-1234-5678-ABCD",
+            "deviceId": "babe078d",
+            "events": [{"properties": {"token": "21c5f5354133116a", "email": "jane@example.com"}}]
+        });
+        let redacted: Value =
+            serde_json::from_str(&redact_body("application/json", body.to_string().as_bytes()))
+                .unwrap();
+        assert_eq!(redacted["licenseKey"], "****ABCD");
+        assert_eq!(redacted["deviceId"], "babe078d");
+        assert_eq!(redacted["events"][0]["properties"]["token"], REDACTED);
+        assert_eq!(redacted["events"][0]["properties"]["email"], "j***@example.com");
+
+        let form: Value = serde_json::from_str(&redact_body(
+            "application/x-www-form-urlencoded",
+            b"email=jane%40example.com&did=babe078d&subscribe=true",
+        ))
+        .unwrap();
+        assert_eq!(form["email"], "j***@example.com");
+        assert_eq!(form["did"], "babe078d");
+    }
+
+    #[test]
+    fn test_redact_query_and_headers() {
+        let url =
+            Url::parse("https://just-drink.app/validate?device-id=babe078d&email=jane%40example.com")
+                .unwrap();
+        assert_eq!(
+            redact_url(&url),
+            "https://just-drink.app/validate?device-id=babe078d&email=j***%40example.com"
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert("origin", "FREEMIUM_APP".parse().unwrap());
+        headers.insert(AUTHORIZATION, "Bearer 21c5f5354133116a".parse().unwrap());
+        headers.insert(CONTENT_TYPE, "application/json".parse().unwrap());
+        let headers = redact_headers(&headers);
+        assert_eq!(headers["origin"], "FREEMIUM_APP");
+        assert_eq!(headers["authorization"], REDACTED);
+        assert!(!headers.contains_key("content-type"));
+    }
+
+    #[test]
+    fn test_redact_multipart() {
+        let form = MultipartForm::new()
+            .json(
+                "feedback",
+                &json!({"email": "jane@example.com", "message": "Love it"}),
+            )
+            .file("attachment", "crash-report.txt", "text/plain", b"panicked at main.rs");
+        let content_type = form.content_type();
+
+        let redacted: Value =
+            serde_json::from_str(&redact_body(&content_type, &form.into_body())).unwrap();
+        assert_eq!(redacted["feedback"]["email"], "j***@example.com");
+        assert_eq!(redacted["feedback"]["message"], "Love it");
+        assert_eq!(
+            redacted["attachments"],
+            json!([{"name": "attachment", "fileName": "crash-report.txt", "size": 19}])
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use crate::model::settings::AutostartStatus;
+use log::{info, warn};
+use std::sync::Mutex;
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_autostart::ManagerExt;
+
+pub(crate) struct Autostart {
+    status: Mutex<AutostartStatus>,
+}
+
+impl Autostart {
+    pub fn new() -> Self {
+        Autostart {
+            status: Mutex::new(AutostartStatus {
+                enabled: false,
+                error: None,
+            }),
+        }
+    }
+
+    /// Aligns the os launch entry with `enable_on_startup`. An entry, which
+    /// can't be read, is written again.
+    pub fn reconcile<R: Runtime>(&self, app: &AppHandle<R>, enable_on_startup: bool) -> AutostartStatus {
+        let status = match Self::apply(app, enable_on_startup) {
+            Ok(enabled) => AutostartStatus {
+                enabled,
+                error: if enabled == enable_on_startup {
+                    None
+                } else {
+                    Some("The launch entry could not be changed by the system.".to_string())
+                },
+            },
+            Err(err) => {
+                warn!("unable to reconcile autostart: {:?}", err);
+                AutostartStatus {
+                    enabled: app.autolaunch().is_enabled().unwrap_or(false),
+                    error: Some(err.to_string()),
+                }
+            }
+        };
+
+        *self.status.lock().expect("autostart status should not be poisoned") = status.clone();
+        status
+    }
+
+    pub fn status(&self) -> AutostartStatus {
+        self.status
+            .lock()
+            .expect("autostart status should not be poisoned")
+            .clone()
+    }
+
+    fn apply<R: Runtime>(app: &AppHandle<R>, enable_on_startup: bool) -> Result<bool, anyhow::Error> {
+        let launcher = app.autolaunch();
+        let (enabled, broken) = match launcher.is_enabled() {
+            Ok(enabled) => (enabled, false),
+            Err(err) => {
+                warn!("autostart entry is broken: {:?}", err);
+                (true, true)
+            }
+        };
+
+        if enable_on_startup {
+            if !enabled || broken {
+                info!("enable autostart, repair: {broken}");
+                if broken {
+                    launcher.disable().unwrap_or_else(|err| {
+                        warn!("unable to remove old autostart entry: {:?}", err)
+                    });
+                }
+                launcher.enable()?;
+            }
+        } else if enabled {
+            info!("disable autostart");
+            launcher.disable()?;
+        }
+
+        Ok(launcher.is_enabled()?)
+    }
+}
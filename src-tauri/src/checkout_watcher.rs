@@ -0,0 +1,117 @@
+use crate::license_manager::{LicenseResult, LicenseResultStatus};
+use crate::model::license::{LicenseInfoStatus, LicensePaymentStatus};
+use crate::{license_scheduler, CheckoutWatcherState, LicenseManagerState};
+use log::{info, warn};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tauri_specta::Event;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+const CHECKOUT_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+/// Follows a checkout in the browser, until it is paid, canceled, failed or
+/// timed out.
+pub(crate) struct CheckoutWatcher {
+    generation: AtomicU64,
+}
+
+impl CheckoutWatcher {
+    pub fn new() -> Self {
+        CheckoutWatcher {
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    /// Starts watching the checkout. A previously started watch is stopped.
+    pub fn watch(&self, app: &AppHandle) {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let app = app.clone();
+        info!("watch checkout");
+        tauri::async_runtime::spawn(async move {
+            let started = Instant::now();
+            let mut last_payment_status: Option<LicensePaymentStatus> = None;
+            loop {
+                if !app.state::<CheckoutWatcherState>().is_current(generation) {
+                    info!("checkout watch replaced by a newer one");
+                    return;
+                }
+                if started.elapsed() > CHECKOUT_TIMEOUT {
+                    warn!("checkout timed out");
+                    emit(&app, LicenseResultStatus::TimedOut, last_payment_status, None);
+                    return;
+                }
+
+                match app.state::<LicenseManagerState>().poll_license_status().await {
+                    Ok(license_data) => {
+                        let license = license_data.to_model();
+                        let payment_status = license.payment.payment_status.clone();
+                        // a payment ready to capture is pending, until the server captured it
+                        if matches!(license.info.status, LicenseInfoStatus::Paid)
+                            || payment_status == LicensePaymentStatus::Paid
+                        {
+                            info!("checkout paid: {:?}", payment_status);
+                            license_scheduler::publish(&app, license);
+                            emit(&app, LicenseResultStatus::Success, Some(payment_status), None);
+                            return;
+                        }
+                        if payment_status == LicensePaymentStatus::Canceled {
+                            // until the checkout in the browser started, the status
+                            // belongs to an earlier checkout
+                            if last_payment_status.is_none() {
+                                info!("ignore canceled status of an earlier checkout");
+                            } else {
+                                info!("checkout canceled");
+                                emit(&app, LicenseResultStatus::Canceled, Some(payment_status), None);
+                                return;
+                            }
+                        } else if last_payment_status.as_ref() != Some(&payment_status) {
+                            info!("checkout status: {:?}", payment_status);
+                            emit(
+                                &app,
+                                LicenseResultStatus::Pending,
+                                Some(payment_status.clone()),
+                                None,
+                            );
+                            last_payment_status = Some(payment_status);
+                        }
+                    }
+                    Err(err) if err.is_retryable() => {
+                        warn!("checkout status not available, retry: {:?}", err);
+                    }
+                    Err(err) => {
+                        warn!("checkout failed: {:?}", err);
+                        emit(
+                            &app,
+                            LicenseResultStatus::Error,
+                            last_payment_status,
+                            Some(err.to_string()),
+                        );
+                        return;
+                    }
+                }
+
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        });
+    }
+
+    fn is_current(&self, generation: u64) -> bool {
+        self.generation.load(Ordering::SeqCst) == generation
+    }
+}
+
+fn emit(
+    app: &AppHandle,
+    status: LicenseResultStatus,
+    payment_status: Option<LicensePaymentStatus>,
+    error: Option<String>,
+) {
+    LicenseResult {
+        status,
+        payment_status,
+        error,
+    }
+    .emit(app)
+    .unwrap_or_else(|err| warn!("unable to emit license result: {:?}", err));
+}
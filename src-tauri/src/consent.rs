@@ -0,0 +1,141 @@
+use crate::model::consent::TelemetryConsent;
+use crate::persistence::{Persisted, Persistence};
+use crate::tracking::Event;
+use crate::{settings_manager, PersistenceState, SettingsManagerState, TrackingState};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+use tauri::State;
+
+impl Persisted for TelemetryConsent {
+    const FILE_NAME: &'static str = "mm-consent.json";
+    const ROOT_KEY: &'static str = "consent";
+}
+
+/// Kept outside of the managed state, so the panic hook can check it as well.
+static CONSENT: RwLock<TelemetryConsent> = RwLock::new(TelemetryConsent::UNANSWERED);
+
+/// The opt-in, which covers a telemetry event.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TelemetryKind {
+    UsageAnalytics,
+    CrashReports,
+    ErrorReports,
+}
+
+/// Loads the stored answer. Until then, nothing is allowed.
+pub fn init(persistence: &Persistence) {
+    let consent = persistence
+        .load::<TelemetryConsent>()
+        .unwrap_or_else(|err| {
+            warn!("consent not readable, ask again: {:?}", err);
+            None
+        })
+        .or_else(|| migrate(persistence))
+        .unwrap_or(TelemetryConsent::UNANSWERED);
+    info!("telemetry consent: {:?}", consent);
+    set(consent);
+}
+
+/// Older versions asked once with `allow_tracking`, which covered all telemetry.
+fn migrate(persistence: &Persistence) -> Option<TelemetryConsent> {
+    let allow_tracking = settings_manager::legacy_allow_tracking(persistence)?;
+    let consent = TelemetryConsent {
+        answered: true,
+        usage_analytics: allow_tracking,
+        crash_reports: allow_tracking,
+        error_reports: allow_tracking,
+    };
+    info!("migrate allow tracking to the telemetry consent: {:?}", consent);
+    persistence
+        .save(&consent)
+        .unwrap_or_else(|err| warn!("unable to store migrated consent: {:?}", err));
+    Some(consent)
+}
+
+pub fn current() -> TelemetryConsent {
+    CONSENT
+        .read()
+        .map(|consent| consent.clone())
+        .unwrap_or(TelemetryConsent::UNANSWERED)
+}
+
+/// True, if the user answered the consent question and opted in.
+pub fn allows(kind: TelemetryKind) -> bool {
+    let consent = current();
+    consent.answered
+        && match kind {
+            TelemetryKind::UsageAnalytics => consent.usage_analytics,
+            TelemetryKind::CrashReports => consent.crash_reports,
+            TelemetryKind::ErrorReports => consent.error_reports,
+        }
+}
+
+/// Forgets the answer until the next start, e.g. after the data was deleted.
+pub fn reset() {
+    set(TelemetryConsent::UNANSWERED);
+}
+
+fn set(consent: TelemetryConsent) {
+    match CONSENT.write() {
+        Ok(mut current) => *current = consent,
+        Err(err) => warn!("unable to update consent: {:?}", err),
+    }
+}
+
+#[specta::specta]
+#[tauri::command]
+pub fn get_telemetry_consent() -> TelemetryConsent {
+    current()
+}
+
+/// The first answer during the welcome wizard of a new installation tracks
+/// the install, since nothing is tracked before.
+#[specta::specta]
+#[tauri::command]
+pub async fn set_telemetry_consent(
+    consent: TelemetryConsent,
+    persistence: State<'_, PersistenceState>,
+    settings_manager: State<'_, SettingsManagerState>,
+    tracking: State<'_, TrackingState>,
+) -> Result<(), String> {
+    let consent = TelemetryConsent {
+        answered: true,
+        ..consent
+    };
+    info!("update telemetry consent: {:?}", consent);
+    persistence.save(&consent).map_err(|err| err.to_string())?;
+    let first_answer = !current().answered;
+    set(consent);
+
+    if first_answer && settings_manager.get_settings().is_none() {
+        tracking.send_tracking(Event::Install).await;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nothing_allowed_before_answer() {
+        set(TelemetryConsent {
+            answered: false,
+            usage_analytics: true,
+            crash_reports: true,
+            error_reports: true,
+        });
+        assert!(!allows(TelemetryKind::UsageAnalytics));
+
+        set(TelemetryConsent {
+            answered: true,
+            usage_analytics: true,
+            crash_reports: true,
+            error_reports: false,
+        });
+        assert!(allows(TelemetryKind::CrashReports));
+        assert!(!allows(TelemetryKind::ErrorReports));
+    }
+}
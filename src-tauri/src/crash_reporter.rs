@@ -0,0 +1,259 @@
+use crate::audit_log;
+use crate::model::settings::SettingsSystemDetails;
+use crate::persistence::Persisted;
+use crate::settings_manager::UserSettingsStore;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use specta::Type;
+use std::backtrace::Backtrace;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tauri::{AppHandle, Manager};
+
+const REPORT_DIR: &str = "crash-reports";
+const LOG_FILE_NAME: &str = "justdrink.log";
+const MAX_REPORTS: usize = 5;
+const MAX_LOG_LINES: usize = 200;
+
+static CRASH_REPORTER: OnceLock<CrashReporter> = OnceLock::new();
+
+/// Everything we know about a panic. It is kept locally and only leaves the
+/// machine, if the user attaches it to a feedback.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct CrashReport {
+    pub id: String,
+    pub created_at: DateTime<Utc>,
+    pub app_version: String,
+    pub platform: String,
+    pub arch: String,
+    pub thread: String,
+    pub message: String,
+    pub location: String,
+    pub backtrace: String,
+    pub log_lines: Vec<String>,
+    /// The stored settings, without secrets like redeemed codes.
+    pub settings: Map<String, Value>,
+}
+
+/// The part of a crash report, which is shown when offering to send it.
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+pub struct CrashReportSummary {
+    pub(crate) id: String,
+    pub(crate) created_at: String,
+    pub(crate) app_version: String,
+    pub(crate) message: String,
+}
+
+struct CrashReporter {
+    report_dir: PathBuf,
+    data_dir: PathBuf,
+    log_file: PathBuf,
+    app_version: String,
+}
+
+/// Writes a crash report for every panic. The previous panic hook, e.g. the one
+/// of the Aptabase plugin, is still called.
+pub fn init(app: &AppHandle) -> Result<(), anyhow::Error> {
+    let data_dir = app.path().app_data_dir()?;
+    let reporter = CrashReporter {
+        report_dir: data_dir.join(REPORT_DIR),
+        data_dir,
+        log_file: app.path().app_log_dir()?.join(LOG_FILE_NAME),
+        app_version: app.package_info().version.to_string(),
+    };
+    info!("crash reports in {:?}", reporter.report_dir);
+    if CRASH_REPORTER.set(reporter).is_err() {
+        return Ok(());
+    }
+
+    let previous_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if let Some(reporter) = CRASH_REPORTER.get() {
+            let message = info
+                .payload()
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| info.payload().downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            let location = info
+                .location()
+                .map(|loc| format!("{}:{}:{}", loc.file(), loc.line(), loc.column()))
+                .unwrap_or_default();
+            match reporter.write(message, location) {
+                Ok(path) => error!("panic, crash report written to {:?}", path),
+                Err(err) => error!("panic, unable to write crash report: {:?}", err),
+            }
+        }
+        previous_hook(info);
+    }));
+    Ok(())
+}
+
+/// The newest crash report, which wasn't sent or dismissed yet.
+pub fn latest() -> Option<CrashReportSummary> {
+    let reporter = CRASH_REPORTER.get()?;
+    list_reports(&reporter.report_dir)
+        .into_iter()
+        .next()
+        .and_then(|path| read_report(&path).ok())
+        .map(|report| CrashReportSummary {
+            id: report.id,
+            created_at: report.created_at.to_rfc3339(),
+            app_version: report.app_version,
+            message: report.message,
+        })
+}
+
+/// The full report as JSON, to attach it to a feedback.
+pub fn report_json(id: &str) -> Result<String, anyhow::Error> {
+    let path = report_path(id)?;
+    fs::read_to_string(&path).with_context(|| format!("unable to read crash report {:?}", path))
+}
+
+pub fn remove(id: &str) -> Result<(), anyhow::Error> {
+    let path = report_path(id)?;
+    info!("remove crash report {:?}", path);
+    match fs::remove_file(&path) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+
+#[specta::specta]
+#[tauri::command]
+pub fn pending_crash_report() -> Option<CrashReportSummary> {
+    latest()
+}
+
+/// The user doesn't want to send the report, it isn't offered again.
+#[specta::specta]
+#[tauri::command]
+pub fn dismiss_crash_report(id: String) -> Result<(), String> {
+    remove(&id).map_err(|err| err.to_string())
+}
+
+impl CrashReporter {
+    fn write(&self, message: String, location: String) -> Result<PathBuf, anyhow::Error> {
+        let created_at = Utc::now();
+        let report = CrashReport {
+            id: format!("crash-{}", created_at.timestamp_millis()),
+            created_at,
+            app_version: self.app_version.clone(),
+            platform: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            thread: std::thread::current()
+                .name()
+                .unwrap_or("unnamed")
+                .to_string(),
+            message,
+            location,
+            backtrace: Backtrace::force_capture().to_string(),
+            log_lines: last_lines(&self.log_file, MAX_LOG_LINES),
+            settings: sanitized_settings(&self.data_dir),
+        };
+
+        fs::create_dir_all(&self.report_dir)?;
+        let path = self.report_dir.join(format!("{}.json", report.id));
+        fs::write(&path, serde_json::to_string_pretty(&report)?)?;
+        for outdated in list_reports(&self.report_dir).into_iter().skip(MAX_REPORTS) {
+            fs::remove_file(&outdated)
+                .unwrap_or_else(|err| warn!("unable to remove {:?}: {:?}", outdated, err));
+        }
+        Ok(path)
+    }
+}
+
+fn report_path(id: &str) -> Result<PathBuf, anyhow::Error> {
+    let reporter = CRASH_REPORTER
+        .get()
+        .ok_or_else(|| anyhow::anyhow!("crash reporter is not initialized"))?;
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(anyhow::anyhow!("invalid crash report id '{}'", id));
+    }
+    Ok(reporter.report_dir.join(format!("{}.json", id)))
+}
+
+/// The report files, newest first.
+fn list_reports(dir: &Path) -> Vec<PathBuf> {
+    let mut reports: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+                .collect()
+        })
+        .unwrap_or_default();
+    reports.sort();
+    reports.reverse();
+    reports
+}
+
+fn read_report(path: &Path) -> Result<CrashReport, anyhow::Error> {
+    let content = fs::read_to_string(path)?;
+    serde_json::from_str(&content).with_context(|| format!("invalid crash report {:?}", path))
+}
+
+fn last_lines(path: &Path, count: usize) -> Vec<String> {
+    let content = fs::read(path).unwrap_or_default();
+    let content = String::from_utf8_lossy(&content);
+    let lines: Vec<&str> = content.lines().collect();
+    lines[lines.len().saturating_sub(count)..]
+        .iter()
+        .map(|line| line.to_string())
+        .collect()
+}
+
+/// The stored settings, secrets are redacted like in the privacy audit log.
+pub(crate) fn sanitized_settings(data_dir: &Path) -> Map<String, Value> {
+    [
+        UserSettingsStore::FILE_NAME,
+        SettingsSystemDetails::FILE_NAME,
+    ]
+    .into_iter()
+    .filter_map(|file_name| {
+        let content = fs::read_to_string(data_dir.join(file_name)).ok()?;
+        let settings = serde_json::from_str::<Value>(&content).ok()?;
+        Some((file_name.to_string(), audit_log::redact_json(settings)))
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_contains_recent_logs_and_sanitized_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_file = dir.path().join(LOG_FILE_NAME);
+        let lines: Vec<String> = (0..250).map(|index| format!("line {}", index)).collect();
+        fs::write(&log_file, lines.join("\n")).unwrap();
+        fs::write(
+            dir.path().join(SettingsSystemDetails::FILE_NAME),
+            r#"{"system": {"session_count": 3, "redeemed_codes": [{"code": "SUMMER-2024"}]}}"#,
+        )
+        .unwrap();
+        let reporter = CrashReporter {
+            report_dir: dir.path().join(REPORT_DIR),
+            data_dir: dir.path().to_path_buf(),
+            log_file,
+            app_version: "1.0.0".to_string(),
+        };
+
+        let path = reporter
+            .write("boom".to_string(), "src/lib.rs:1:1".to_string())
+            .unwrap();
+        let report = read_report(&path).unwrap();
+        assert_eq!(report.log_lines.len(), MAX_LOG_LINES);
+        assert_eq!(report.log_lines[0], "line 50");
+        let system = &report.settings[SettingsSystemDetails::FILE_NAME]["system"];
+        assert_eq!(system["session_count"], 3);
+        assert_eq!(system["redeemed_codes"][0]["code"], "****2024");
+        assert_eq!(list_reports(&reporter.report_dir), vec![path]);
+    }
+}
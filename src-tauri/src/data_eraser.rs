@@ -0,0 +1,200 @@
+use crate::app_config::AppConfig;
+use crate::http_client::HttpClient;
+use crate::model::device::DeviceId;
+use crate::server_error::ServerError;
+use crate::{
+    consent, CountdownTimerState, DataEraserState, PersistenceState, SubscriptionManagerState,
+};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, State};
+
+/// Outcome of the erasure request for the data stored on our servers.
+#[derive(Serialize, Deserialize, Debug, Clone, Type, PartialEq)]
+pub enum ErasureStatus {
+    /// The server deletes everything, which belongs to the device.
+    Accepted,
+    /// The server has nothing stored for the device.
+    NothingStored,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+pub struct DataDeletionResult {
+    pub(crate) erasure: ErasureStatus,
+    /// Reference of the erasure request, for questions to our support.
+    pub(crate) reference: Option<String>,
+    pub(crate) newsletter_unsubscribed: bool,
+    /// Files, which couldn't be deleted, e.g. because they are still in use.
+    pub(crate) remaining_files: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct ErasureRequest {
+    #[serde(rename = "deviceId")]
+    device_id: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct ErasureResponse {
+    status: String,
+    #[serde(default)]
+    reference: Option<String>,
+}
+
+/// Deletes everything we know about the device, locally and on our servers.
+pub(crate) struct DataEraser {
+    device_id: DeviceId,
+    client: HttpClient,
+}
+
+impl DataEraser {
+    pub fn new(device_id: &DeviceId, client: HttpClient) -> Self {
+        DataEraser {
+            device_id: device_id.clone(),
+            client,
+        }
+    }
+
+    /// Asks the backend to delete the license, newsletter and feedback records
+    /// of the hashed device id.
+    pub async fn request_erasure(&self) -> Result<(ErasureStatus, Option<String>), ServerError> {
+        let url = format!("{}/app/v1/privacy/erasure", AppConfig::build().get_url());
+        let request = ErasureRequest {
+            device_id: self.device_id.get_hash_hex_id(),
+        };
+        let response = match self
+            .client
+            .send_idempotent(self.client.post(&url).json(&request))
+            .await
+        {
+            Ok(response) => response,
+            Err(ServerError::Rejected { status: 404, .. }) => {
+                return Ok((ErasureStatus::NothingStored, None))
+            }
+            Err(err) => return Err(err),
+        };
+
+        let response: ErasureResponse = response.json().await.map_err(ServerError::bad_payload)?;
+        match response.status.as_str() {
+            "ACCEPTED" => Ok((ErasureStatus::Accepted, response.reference)),
+            "NOT_FOUND" => Ok((ErasureStatus::NothingStored, response.reference)),
+            status => Err(ServerError::bad_payload(format!(
+                "unknown erasure status '{}'",
+                status
+            ))),
+        }
+    }
+}
+
+/// Removes the content of the directory. Files, which can't be removed, are returned.
+fn delete_dir_content(dir: &Path) -> Vec<String> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Vec::new(),
+        Err(err) => {
+            warn!("unable to read {:?}: {:?}", dir, err);
+            return vec![dir.to_string_lossy().to_string()];
+        }
+    };
+
+    let mut remaining = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let result = if path.is_dir() {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        };
+        match result {
+            Ok(_) => info!("deleted {:?}", path),
+            Err(err) => {
+                warn!("unable to delete {:?}: {:?}", path, err);
+                remaining.push(path.to_string_lossy().to_string());
+            }
+        }
+    }
+    remaining
+}
+
+/// Sends the erasure request, unsubscribes from the newsletter and deletes all
+/// local stores, caches and logs. Nothing is deleted, if the erasure request
+/// fails.
+#[specta::specta]
+#[tauri::command]
+pub async fn delete_my_data(
+    app: AppHandle,
+    data_eraser: State<'_, DataEraserState>,
+    subscription_manager: State<'_, SubscriptionManagerState>,
+    timer: State<'_, CountdownTimerState>,
+    persistence: State<'_, PersistenceState>,
+) -> Result<DataDeletionResult, ServerError> {
+    info!("delete my data");
+    let (erasure, reference) = data_eraser.request_erasure().await.inspect_err(|err| {
+        warn!("erasure request failed, keep local data: {:?}", err);
+    })?;
+
+    let newsletter_unsubscribed = subscription_manager
+        .unsubscribe()
+        .await
+        .inspect_err(|err| warn!("unable to unsubscribe from newsletter: {:?}", err))
+        .is_ok();
+
+    // nothing may be tracked or stored anymore until the restart, e.g. the
+    // tracking queue on quit
+    consent::reset();
+    timer.stop();
+    persistence
+        .close()
+        .unwrap_or_else(|err| warn!("unable to close the stores: {:?}", err));
+
+    let mut dirs: Vec<PathBuf> = [
+        app.path().app_data_dir(),
+        app.path().app_local_data_dir(),
+        app.path().app_cache_dir(),
+        app.path().app_log_dir(),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    // the directories are the same on some platforms
+    dirs.dedup();
+    let mut remaining_files = Vec::new();
+    for dir in dirs {
+        remaining_files.extend(delete_dir_content(&dir));
+    }
+
+    Ok(DataDeletionResult {
+        erasure,
+        reference,
+        newsletter_unsubscribed,
+        remaining_files,
+    })
+}
+
+/// Restarts into the welcome wizard, after the data has been deleted.
+#[specta::specta]
+#[tauri::command]
+pub fn restart_after_data_deletion(app: AppHandle) {
+    info!("restart after data deletion");
+    app.restart();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delete_dir_content() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("mm-config.json"), "{}").unwrap();
+        fs::create_dir(dir.path().join("backups")).unwrap();
+        fs::write(dir.path().join("backups").join("mm-config.json.1"), "{}").unwrap();
+
+        assert!(delete_dir_content(dir.path()).is_empty());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+        assert!(delete_dir_content(&dir.path().join("missing")).is_empty());
+    }
+}
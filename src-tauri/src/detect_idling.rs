@@ -1,6 +1,7 @@
 use crate::countdown_timer::{PauseOrigin, TimerStatus};
-use crate::{session_window, CountdownTimerState, SettingsManagerState};
+use crate::{session_window, updater_window, CountdownTimerState, SettingsManagerState};
 use log::{debug, warn};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread::sleep;
 use std::time::Duration;
 use tauri::{AppHandle, Manager, Wry};
@@ -9,11 +10,18 @@ use user_idle::UserIdle;
 const IDLE_DURATION_S: u64 = 60;
 const MIN_ACTIVE_DURATION_S: u64 = 20;
 
+static IDLE_PAUSE: AtomicBool = AtomicBool::new(false);
+
 pub enum Mode {
     Pause,
     Working,
 }
 
+/// Whether the idle detection switched to pause, e.g. for diagnostics.
+pub fn is_idle_pause() -> bool {
+    IDLE_PAUSE.load(Ordering::Relaxed)
+}
+
 pub fn init(app: &AppHandle<Wry>) -> Result<(), anyhow::Error> {
     let app_handle = app.app_handle().clone();
     tauri::async_runtime::spawn(async move {
@@ -50,6 +58,7 @@ pub fn init(app: &AppHandle<Wry>) -> Result<(), anyhow::Error> {
                                     timer.resume();
                                 }
                                 mode = Mode::Working;
+                                IDLE_PAUSE.store(false, Ordering::Relaxed);
                             }
                         }
                         Mode::Working => {
@@ -60,6 +69,10 @@ pub fn init(app: &AppHandle<Wry>) -> Result<(), anyhow::Error> {
                                 });
                                 timer.pause(PauseOrigin::Idle);
                                 mode = Mode::Pause;
+                                IDLE_PAUSE.store(true, Ordering::Relaxed);
+                                updater_window::install_downloaded_and_restart(
+                                    app_handle.app_handle(),
+                                );
                             }
                         }
                     }
@@ -0,0 +1,213 @@
+use crate::app_config::AppConfig;
+use crate::{audit_log, crash_reporter, detect_idling, CountdownTimerState, LicenseManagerState};
+use anyhow::Context;
+use chrono::Utc;
+use log::{info, warn};
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use user_idle::UserIdle;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Creates the bundle on start, or in the running instance.
+const DIAGNOSTICS_BUNDLE_ARG: &str = "--diagnostics-bundle";
+const LOG_FILE_PREFIX: &str = "justdrink";
+
+/// Creates the bundle in the background, if the flag is part of the arguments.
+pub fn handle_args(app: &AppHandle, args: &[String]) {
+    if !args.iter().any(|arg| arg == DIAGNOSTICS_BUNDLE_ARG) {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(err) = create_and_reveal(&app).await {
+            warn!("unable to create diagnostics bundle: {:?}", err);
+        }
+    });
+}
+
+/// Collects logs, settings and the app state for a support ticket. Secrets are
+/// removed, the zip is shown in the file manager.
+#[specta::specta]
+#[tauri::command]
+pub async fn create_diagnostics_bundle(app: AppHandle) -> Result<String, String> {
+    create_and_reveal(&app)
+        .await
+        .map(|path| path.to_string_lossy().to_string())
+        .map_err(|err| err.to_string())
+}
+
+async fn create_and_reveal(app: &AppHandle) -> Result<PathBuf, anyhow::Error> {
+    let target_dir = match app.path().download_dir() {
+        Ok(dir) => dir,
+        Err(_) => app.path().app_log_dir()?,
+    };
+    let path = target_dir.join(format!(
+        "justdrink-diagnostics-{}.zip",
+        Utc::now().format("%Y%m%d-%H%M%S")
+    ));
+
+    let bundle = create_bundle(app).await?;
+    fs::create_dir_all(&target_dir)?;
+    fs::write(&path, bundle).with_context(|| format!("unable to write {:?}", path))?;
+    info!("diagnostics bundle written to {:?}", path);
+    reveal(&path).unwrap_or_else(|err| warn!("unable to reveal {:?}: {:?}", path, err));
+    Ok(path)
+}
+
+/// The zip with logs, settings and app state, e.g. to attach it to a feedback.
+pub async fn create_bundle(app: &AppHandle) -> Result<Vec<u8>, anyhow::Error> {
+    let log_dir = app.path().app_log_dir()?;
+    let mut entries = Vec::new();
+    for log_file in log_files(&log_dir) {
+        let name = log_file
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        match fs::read(&log_file) {
+            Ok(content) => entries.push((format!("logs/{}", name), content)),
+            Err(err) => warn!("unable to read {:?}: {:?}", log_file, err),
+        }
+    }
+    let settings = crash_reporter::sanitized_settings(&app.path().app_data_dir()?);
+    entries.push((
+        "settings.json".to_string(),
+        to_json(&Value::Object(settings))?,
+    ));
+    entries.push(("state.json".to_string(), to_json(&app_state(app).await)?));
+    entries.push(("system.json".to_string(), to_json(&system_info(app))?));
+
+    zip_entries(&entries)
+}
+
+async fn app_state(app: &AppHandle) -> Value {
+    let timer_status = app
+        .try_state::<CountdownTimerState>()
+        .map(|timer| json!(timer.timer_status()));
+    let license = match app.try_state::<LicenseManagerState>() {
+        Some(license_manager) => match license_manager.get_status(app, true, false).await {
+            Ok(data) => audit_log::redact_json(json!(data.to_model())),
+            Err(err) => json!({ "error": err }),
+        },
+        None => Value::Null,
+    };
+    json!({
+        "timer_status": timer_status,
+        "idle": {
+            "paused_by_idle_detection": detect_idling::is_idle_pause(),
+            "idle_seconds": UserIdle::get_time().map(|idle| idle.as_seconds()).ok(),
+        },
+        "license": license,
+    })
+}
+
+fn system_info(app: &AppHandle) -> Value {
+    let app_config = AppConfig::build();
+    json!({
+        "app_version": app.package_info().version.to_string(),
+        "platform": tauri_plugin_os::platform(),
+        "os_version": tauri_plugin_os::version().to_string(),
+        "arch": tauri_plugin_os::arch(),
+        "locale": sys_locale::get_locale(),
+        "custom_backend": app_config.is_custom_backend(),
+        "created_at": Utc::now().to_rfc3339(),
+    })
+}
+
+fn to_json(value: &Value) -> Result<Vec<u8>, anyhow::Error> {
+    Ok(serde_json::to_vec_pretty(value)?)
+}
+
+/// The current and rotated log files of the LogDir target.
+fn log_files(log_dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(log_dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    name.starts_with(LOG_FILE_PREFIX) && name.ends_with(".log")
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+fn zip_entries(entries: &[(String, Vec<u8>)]) -> Result<Vec<u8>, anyhow::Error> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    for (name, content) in entries {
+        zip.start_file(name.as_str(), options)?;
+        zip.write_all(content)?;
+    }
+    Ok(zip.finish()?.into_inner())
+}
+
+fn reveal(path: &Path) -> Result<(), anyhow::Error> {
+    #[cfg(target_os = "macos")]
+    let status = std::process::Command::new("open")
+        .arg("-R")
+        .arg(path)
+        .status()?;
+    #[cfg(target_os = "windows")]
+    let status = std::process::Command::new("explorer")
+        .arg(format!("/select,{}", path.display()))
+        .status()?;
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let status = std::process::Command::new("xdg-open")
+        .arg(path.parent().unwrap_or(path))
+        .status()?;
+
+    // explorer reports a failure, even if the file is shown
+    if !status.success() && !cfg!(target_os = "windows") {
+        return Err(anyhow::anyhow!("file manager failed with {}", status));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_bundle_contains_rotated_logs() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("justdrink.log"), "current").unwrap();
+        fs::write(
+            dir.path().join("justdrink_2024-05-01_10-00-00.log"),
+            "rotated",
+        )
+        .unwrap();
+        fs::write(dir.path().join("privacy-audit.jsonl"), "{}").unwrap();
+
+        let files = log_files(dir.path());
+        assert_eq!(files.len(), 2);
+
+        let entries: Vec<(String, Vec<u8>)> = files
+            .iter()
+            .map(|file| {
+                let name = file.file_name().unwrap().to_string_lossy().to_string();
+                (format!("logs/{}", name), fs::read(file).unwrap())
+            })
+            .collect();
+        let bundle = zip_entries(&entries).unwrap();
+
+        let mut archive = zip::ZipArchive::new(Cursor::new(bundle)).unwrap();
+        let mut content = String::new();
+        archive
+            .by_name("logs/justdrink.log")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "current");
+        assert_eq!(archive.len(), 2);
+    }
+}
@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// How bad an error is for the user.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Type)]
+pub enum Severity {
+    /// Something didn't work, but the user can simply try again.
+    Warning,
+    /// A feature is not available until the problem is solved.
+    Error,
+    /// The app can't work properly anymore. Never rate limited.
+    Critical,
+}
+
+/// Every error, which is shown to the user. The codes are stable, so support
+/// requests and error reports can be matched across versions.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Type)]
+pub enum ErrorCode {
+    DashboardUnavailable,
+    SettingsUnavailable,
+    FeedbackUnavailable,
+    UpdaterUnavailable,
+    ActionMenuUnavailable,
+    SessionWindowMissing,
+    SessionStartFailed,
+    TrayUpdateFailed,
+    LicenseServerUnreachable,
+    LicenseRejected,
+    LicenseUnverified,
+    SettingsNotSaved,
+    SettingsResetFailed,
+    NewsletterSubscriptionFailed,
+    FeedbackRejected,
+    ReviewUnavailable,
+    BrowserUnavailable,
+    PaymentPageUnavailable,
+    UpdateFailed,
+    RelaunchFailed,
+    ClientError,
+}
+
+impl ErrorCode {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ErrorCode::DashboardUnavailable => "JD-101",
+            ErrorCode::SettingsUnavailable => "JD-102",
+            ErrorCode::FeedbackUnavailable => "JD-103",
+            ErrorCode::UpdaterUnavailable => "JD-104",
+            ErrorCode::ActionMenuUnavailable => "JD-105",
+            ErrorCode::SessionWindowMissing => "JD-201",
+            ErrorCode::SessionStartFailed => "JD-202",
+            ErrorCode::TrayUpdateFailed => "JD-203",
+            ErrorCode::LicenseServerUnreachable => "JD-301",
+            ErrorCode::LicenseRejected => "JD-302",
+            ErrorCode::LicenseUnverified => "JD-303",
+            ErrorCode::SettingsNotSaved => "JD-401",
+            ErrorCode::SettingsResetFailed => "JD-402",
+            ErrorCode::NewsletterSubscriptionFailed => "JD-501",
+            ErrorCode::FeedbackRejected => "JD-502",
+            ErrorCode::ReviewUnavailable => "JD-503",
+            ErrorCode::BrowserUnavailable => "JD-504",
+            ErrorCode::PaymentPageUnavailable => "JD-505",
+            ErrorCode::UpdateFailed => "JD-601",
+            ErrorCode::RelaunchFailed => "JD-602",
+            ErrorCode::ClientError => "JD-901",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            ErrorCode::SessionWindowMissing
+            | ErrorCode::SettingsNotSaved
+            | ErrorCode::ClientError => Severity::Critical,
+            ErrorCode::LicenseRejected
+            | ErrorCode::LicenseUnverified
+            | ErrorCode::SessionStartFailed
+            | ErrorCode::SettingsResetFailed
+            | ErrorCode::UpdateFailed
+            | ErrorCode::RelaunchFailed => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            ErrorCode::DashboardUnavailable => "Error while showing dashboard",
+            ErrorCode::SettingsUnavailable => "Can't open settings",
+            ErrorCode::FeedbackUnavailable => "Can't open feedback",
+            ErrorCode::UpdaterUnavailable => "Can't show update dialog",
+            ErrorCode::ActionMenuUnavailable => "Can't open action menu",
+            ErrorCode::SessionWindowMissing => "Session Window Missing",
+            ErrorCode::SessionStartFailed => "Can't start session",
+            ErrorCode::TrayUpdateFailed => "Can't set timer in tray",
+            ErrorCode::LicenseServerUnreachable => "Unable to access license server",
+            ErrorCode::LicenseRejected => "License Error",
+            ErrorCode::LicenseUnverified => "License can't be verified",
+            ErrorCode::SettingsNotSaved => "Failed to save settings",
+            ErrorCode::SettingsResetFailed => "Unable to reset",
+            ErrorCode::NewsletterSubscriptionFailed => "Can't subscribe",
+            ErrorCode::FeedbackRejected => "Can't send feedback",
+            ErrorCode::ReviewUnavailable => "Can't open Review",
+            ErrorCode::BrowserUnavailable => "Could not open Browser",
+            ErrorCode::PaymentPageUnavailable => "Unable to redirect to payment site",
+            ErrorCode::UpdateFailed => "Update Error",
+            ErrorCode::RelaunchFailed => "Update Error",
+            ErrorCode::ClientError => "Client error",
+        }
+    }
+
+    /// The text for the user. Details, like the reason of a license error, are
+    /// shown below.
+    pub fn message(&self) -> &'static str {
+        match self {
+            ErrorCode::DashboardUnavailable => "I am sorry, we are unable to show the dashboard. Please try again later.",
+            ErrorCode::SettingsUnavailable => "I am sorry, we are unable to open up the settings. Please try again later.",
+            ErrorCode::FeedbackUnavailable => "I am sorry, we are unable to open up feedback.",
+            ErrorCode::UpdaterUnavailable => "I am sorry, we are unable to open the updater.",
+            ErrorCode::ActionMenuUnavailable => "Action Menu can't be opened during new instance. Please try again later.",
+            ErrorCode::SessionWindowMissing => "I am sorry, this should not happen. Please contact Rocket Solutions.",
+            ErrorCode::SessionStartFailed => "There was an error while trying to start the session.",
+            ErrorCode::TrayUpdateFailed => "Unable to update tray.",
+            ErrorCode::LicenseServerUnreachable => "We are sorry, but we have trouble accessing the license server. Please try again later.",
+            ErrorCode::LicenseRejected => "Your license could not be verified.",
+            ErrorCode::LicenseUnverified => "Please connect to the internet, so Just Drink! can verify your license.",
+            ErrorCode::SettingsNotSaved => "I am sorry, I am unable to save your settings. Please contact Rocket Solutions for support.",
+            ErrorCode::SettingsResetFailed => "I am sorry, we are unable to reset the settings. Please try again later.",
+            ErrorCode::NewsletterSubscriptionFailed => "There was an error while subscribing.",
+            ErrorCode::FeedbackRejected => "The feedback was rejected by the server.",
+            ErrorCode::ReviewUnavailable => "Unable to open review in browser.",
+            ErrorCode::BrowserUnavailable => "I am sorry, we are not able to open up the browser.",
+            ErrorCode::PaymentPageUnavailable => "Please ensure that your default browser is working. We are experiencing problems opening the default browser.",
+            ErrorCode::UpdateFailed => "Could not update Just Drink!. Please try again later.",
+            ErrorCode::RelaunchFailed => "Application relaunch failed. Please try restarting manually.",
+            ErrorCode::ClientError => "There was an error while running UI. If the error persists, please contact me at info@rocket-solutions.de.",
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use crate::feedback_window::{Attachment, FeedbackRequest};
+use crate::persistence::{Persisted, Persistence};
+use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+const ATTACHMENT_DIR: &str = "feedback-attachments";
+const MAX_FEEDBACK_AGE_DAYS: i64 = 30;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct StoredAttachment {
+    name: String,
+    file_name: String,
+    content_type: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct QueuedFeedbackEntry {
+    id: u64,
+    request: FeedbackRequest,
+    attachments: Vec<StoredAttachment>,
+    queued_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct FeedbackQueueStore {
+    feedbacks: Vec<QueuedFeedbackEntry>,
+}
+
+impl Persisted for FeedbackQueueStore {
+    const FILE_NAME: &'static str = "mm-feedback-queue.json";
+    const ROOT_KEY: &'static str = "queue";
+}
+
+/// A feedback, which couldn't be sent yet, with its attachments.
+pub(crate) struct QueuedFeedback {
+    pub id: u64,
+    pub request: FeedbackRequest,
+    pub attachments: Vec<Attachment>,
+    pub queued_at: DateTime<Utc>,
+}
+
+/// Feedback, which failed to send e.g. while offline. The queue survives
+/// restarts, attachments are stored next to it.
+pub(crate) struct FeedbackQueue {
+    persistence: Arc<Persistence>,
+    attachment_dir: PathBuf,
+    store: Mutex<FeedbackQueueStore>,
+}
+
+impl FeedbackQueue {
+    pub fn load(persistence: Arc<Persistence>) -> Self {
+        let store = persistence
+            .load::<FeedbackQueueStore>()
+            .unwrap_or_else(|err| {
+                warn!("feedback queue not readable, start empty: {:?}", err);
+                None
+            })
+            .unwrap_or_default();
+        if !store.feedbacks.is_empty() {
+            info!("{} feedbacks queued", store.feedbacks.len());
+        }
+        FeedbackQueue {
+            attachment_dir: persistence.dir().join(ATTACHMENT_DIR),
+            persistence,
+            store: Mutex::new(store),
+        }
+    }
+
+    pub fn push(
+        &self,
+        request: FeedbackRequest,
+        attachments: &[Attachment],
+    ) -> Result<(), anyhow::Error> {
+        let mut store = self.lock();
+        let id = store
+            .feedbacks
+            .iter()
+            .map(|queued| queued.id)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        fs::create_dir_all(&self.attachment_dir)?;
+        for attachment in attachments {
+            let path = self.attachment_path(id, &attachment.file_name);
+            fs::write(&path, &attachment.content)
+                .with_context(|| format!("unable to store attachment {:?}", path))?;
+        }
+        store.feedbacks.push(QueuedFeedbackEntry {
+            id,
+            request,
+            attachments: attachments
+                .iter()
+                .map(|attachment| StoredAttachment {
+                    name: attachment.name.clone(),
+                    file_name: attachment.file_name.clone(),
+                    content_type: attachment.content_type.clone(),
+                })
+                .collect(),
+            queued_at: Utc::now(),
+        });
+        self.persistence.save(&*store)
+    }
+
+    /// The queued feedback, oldest first. Expired feedback is dropped.
+    pub fn all(&self, now: DateTime<Utc>) -> Vec<QueuedFeedback> {
+        let expired: Vec<u64> = self
+            .lock()
+            .feedbacks
+            .iter()
+            .filter(|queued| now - queued.queued_at >= Duration::days(MAX_FEEDBACK_AGE_DAYS))
+            .map(|queued| queued.id)
+            .collect();
+        for id in expired {
+            warn!("drop expired feedback {}", id);
+            self.remove(id);
+        }
+
+        self.lock()
+            .feedbacks
+            .iter()
+            .map(|queued| QueuedFeedback {
+                id: queued.id,
+                request: queued.request.clone(),
+                attachments: queued
+                    .attachments
+                    .iter()
+                    .filter_map(|stored| {
+                        let path = self.attachment_path(queued.id, &stored.file_name);
+                        let content = fs::read(&path)
+                            .inspect_err(|err| warn!("attachment {:?} is lost: {:?}", path, err))
+                            .ok()?;
+                        Some(Attachment {
+                            name: stored.name.clone(),
+                            file_name: stored.file_name.clone(),
+                            content_type: stored.content_type.clone(),
+                            content,
+                        })
+                    })
+                    .collect(),
+                queued_at: queued.queued_at,
+            })
+            .collect()
+    }
+
+    pub fn remove(&self, id: u64) {
+        let mut store = self.lock();
+        if let Some(queued) = store.feedbacks.iter().find(|queued| queued.id == id) {
+            for stored in &queued.attachments {
+                fs::remove_file(self.attachment_path(id, &stored.file_name))
+                    .unwrap_or_else(|err| warn!("unable to remove attachment: {:?}", err));
+            }
+        }
+        store.feedbacks.retain(|queued| queued.id != id);
+        self.persistence
+            .save(&*store)
+            .unwrap_or_else(|err| error!("unable to save feedback queue: {:?}", err));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().feedbacks.is_empty()
+    }
+
+    fn attachment_path(&self, id: u64, file_name: &str) -> PathBuf {
+        self.attachment_dir.join(format!("{}-{}", id, file_name))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, FeedbackQueueStore> {
+        self.store.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feedback_window::{FeedbackCategory, FeedbackRate};
+
+    #[test]
+    fn test_feedback_with_attachment_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let load = || {
+            FeedbackQueue::load(Arc::new(
+                Persistence::with_dir(dir.path().to_path_buf()).unwrap(),
+            ))
+        };
+        let request = FeedbackRequest {
+            device_id: "babe078d".to_string(),
+            feedback: "The reminder is too loud".to_string(),
+            rating: FeedbackRate::OK,
+            category: FeedbackCategory::BUG,
+        };
+        let attachment = Attachment {
+            name: "diagnostics".to_string(),
+            file_name: "diagnostics.zip".to_string(),
+            content_type: "application/zip".to_string(),
+            content: vec![1, 2, 3],
+        };
+        load().push(request, &[attachment]).unwrap();
+
+        let queue = load();
+        let queued = queue.all(Utc::now());
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].request.feedback, "The reminder is too loud");
+        assert_eq!(queued[0].attachments[0].content, vec![1, 2, 3]);
+
+        queue.remove(queued[0].id);
+        assert!(load().is_empty());
+        assert_eq!(
+            fs::read_dir(dir.path().join(ATTACHMENT_DIR))
+                .unwrap()
+                .count(),
+            0
+        );
+    }
+}
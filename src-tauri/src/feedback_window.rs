@@ -1,16 +1,30 @@
 use crate::alert::Alert;
 use crate::app_config::AppConfig;
+use crate::error_catalog::ErrorCode;
+use crate::feedback_queue::FeedbackQueue;
+use crate::http_client::HttpClient;
 use crate::model::device::DeviceId;
-use crate::settings_system::SettingsSystem;
-use crate::{model, FeedbackSenderState, SettingsSystemState};
-use log::error;
+use crate::model::settings::{PromptKind, PromptOutcome};
+use crate::multipart::MultipartForm;
+use crate::persistence::Persistence;
+use crate::server_error::ServerError;
+use crate::{crash_reporter, diagnostics, model, FeedbackSenderState, SettingsSystemState};
+use chrono::Utc;
+use log::{error, info, warn};
 use serde::{Deserialize, Serialize};
 use specta::Type;
+use std::sync::Arc;
+use std::time::Duration;
 use tauri::{AppHandle, Manager, Runtime, State};
-use tauri_plugin_http::reqwest::blocking::Client;
+use tauri_plugin_http::reqwest::header::CONTENT_TYPE;
+use tauri_plugin_notification::NotificationExt;
 use webbrowser::Browser;
 
 pub(crate) const WINDOW_LABEL: &'static str = "feedback";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const ATTACHMENT_TIMEOUT: Duration = Duration::from_secs(120);
+const MIN_RETRY_DELAY: Duration = Duration::from_secs(60);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60 * 60);
 
 pub fn show<R>(app: &AppHandle<R>) -> Result<(), anyhow::Error>
 where
@@ -39,49 +53,203 @@ where
     Ok(())
 }
 
+/// Shows the window with the prompt, which the prompt policy chose.
+pub fn show_prompt(app: &AppHandle, kind: PromptKind) -> Result<(), anyhow::Error> {
+    info!("show {:?} prompt", kind);
+    app.state::<SettingsSystemState>()
+        .lock()
+        .as_mut()
+        .expect("settings_system lock required")
+        .prompt_shown(kind);
+    show(app)
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct FeedbackRequest {
+    pub(crate) device_id: String,
+    pub(crate) feedback: String,
+    pub(crate) rating: FeedbackRate,
+    pub(crate) category: FeedbackCategory,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct NpsRequest {
     device_id: String,
-    feedback: String,
-    rating: FeedbackRate,
+    score: u8,
+    comment: String,
+}
+
+/// A file, which the user chose to send with the feedback.
+#[derive(Debug, Clone)]
+pub(crate) struct Attachment {
+    pub name: String,
+    pub file_name: String,
+    pub content_type: String,
+    pub content: Vec<u8>,
 }
 
 pub(crate) struct FeedbackSender {
     device_id: DeviceId,
-    client: Client,
+    client: HttpClient,
+    queue: FeedbackQueue,
 }
 
 impl FeedbackSender {
-    pub fn new(device_id: &model::device::DeviceId) -> Self {
+    pub fn new(
+        device_id: &model::device::DeviceId,
+        client: HttpClient,
+        persistence: Arc<Persistence>,
+    ) -> Self {
         FeedbackSender {
-            client: Client::new(),
+            client,
             device_id: device_id.clone(),
+            queue: FeedbackQueue::load(persistence),
         }
     }
 
-    pub fn send_feedback(
+    /// Sends the feedback. If the server can't be reached, it is queued and sent
+    /// in the background.
+    pub async fn submit(
         &self,
         feedback: String,
         rating: FeedbackRate,
-    ) -> Result<(), anyhow::Error> {
-        let feedback_request = FeedbackRequest {
+        category: FeedbackCategory,
+        attachments: Vec<Attachment>,
+    ) -> Result<FeedbackDelivery, ServerError> {
+        let request = FeedbackRequest {
             device_id: self.device_id.get_hash_hex_id(),
             feedback,
             rating,
+            category,
         };
+        match self.send_feedback(&request, &attachments).await {
+            Ok(()) => Ok(FeedbackDelivery::Sent),
+            Err(err) if err.is_retryable() => {
+                warn!("unable to send feedback, queue it: {:?}", err);
+                self.queue
+                    .push(request, &attachments)
+                    .map_err(|queue_err| {
+                        error!("unable to queue feedback: {:?}", queue_err);
+                        err
+                    })?;
+                Ok(FeedbackDelivery::Queued)
+            }
+            Err(err) => Err(err),
+        }
+    }
 
-        let response = self
-            .client
-            .post(format!("{}/app/v1/feedback", AppConfig::build().get_url()))
-            .json(&feedback_request)
-            .send();
+    /// Sends the queued feedback and returns, how many were sent. Feedback,
+    /// which the server rejects, is dropped.
+    pub async fn flush(&self) -> Result<usize, ServerError> {
+        let mut sent = 0;
+        for queued in self.queue.all(Utc::now()) {
+            match self.send_feedback(&queued.request, &queued.attachments).await {
+                Ok(()) => {
+                    info!("queued feedback {} from {} sent", queued.id, queued.queued_at);
+                    self.queue.remove(queued.id);
+                    sent += 1;
+                }
+                Err(err) if err.is_retryable() => return Err(err),
+                Err(err) => {
+                    warn!("queued feedback {} rejected, drop it: {:?}", queued.id, err);
+                    self.queue.remove(queued.id);
+                }
+            }
+        }
+        Ok(sent)
+    }
+
+    pub async fn send_nps(&self, score: u8, comment: String) -> Result<(), ServerError> {
+        let url = format!("{}/app/v1/feedback/nps", AppConfig::build().get_url());
+        let request = NpsRequest {
+            device_id: self.device_id.get_hash_hex_id(),
+            score,
+            comment,
+        };
+        self.client
+            .send(self.client.post(&url).timeout(REQUEST_TIMEOUT).json(&request))
+            .await?;
+        Ok(())
+    }
 
-        response?.error_for_status()?;
+    /// Feedback without attachments is sent as JSON, attachments are sent as
+    /// multipart form with the feedback as `feedback` part.
+    async fn send_feedback(
+        &self,
+        request: &FeedbackRequest,
+        attachments: &[Attachment],
+    ) -> Result<(), ServerError> {
+        let url = format!("{}/app/v1/feedback", AppConfig::build().get_url());
+        if attachments.is_empty() {
+            self.client
+                .send(self.client.post(&url).timeout(REQUEST_TIMEOUT).json(request))
+                .await?;
+            return Ok(());
+        }
 
+        let mut form = MultipartForm::new().json(
+            "feedback",
+            &serde_json::to_value(request).map_err(ServerError::bad_payload)?,
+        );
+        for attachment in attachments {
+            form = form.file(
+                &attachment.name,
+                &attachment.file_name,
+                &attachment.content_type,
+                &attachment.content,
+            );
+        }
+        self.client
+            .send(
+                self.client
+                    .post(&url)
+                    .header(CONTENT_TYPE, form.content_type())
+                    .timeout(ATTACHMENT_TIMEOUT)
+                    .body(form.into_body()),
+            )
+            .await?;
         Ok(())
     }
 }
 
+/// Retries the queued feedback in the background and tells the user, when it
+/// was finally sent.
+pub fn init(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut retry_delay = MIN_RETRY_DELAY;
+        loop {
+            let feedback_sender = app.state::<FeedbackSenderState>();
+            if !feedback_sender.queue.is_empty() {
+                match feedback_sender.flush().await {
+                    Ok(sent) => {
+                        retry_delay = MIN_RETRY_DELAY;
+                        if sent > 0 {
+                            notify_sent(&app);
+                        }
+                    }
+                    Err(err) => {
+                        info!("queued feedback not sent, retry in {:?}: {:?}", retry_delay, err);
+                        tokio::time::sleep(retry_delay).await;
+                        retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
+                        continue;
+                    }
+                }
+            }
+            tokio::time::sleep(MIN_RETRY_DELAY).await;
+        }
+    });
+}
+
+fn notify_sent(app: &AppHandle) {
+    app.notification()
+        .builder()
+        .title("Just Drink!")
+        .body("Thank you! Your feedback was sent.")
+        .show()
+        .unwrap_or_else(|err| warn!("unable to show notification: {:?}", err));
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Type)]
 pub enum FeedbackRate {
     UNKNOWN,
@@ -90,30 +258,118 @@ pub enum FeedbackRate {
     AWESOME,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, Type)]
+pub enum FeedbackCategory {
+    GENERAL,
+    BUG,
+    IDEA,
+    BILLING,
+}
+
+/// Whether the feedback reached the server or waits for the connection.
+#[derive(Serialize, Deserialize, Clone, Debug, Type, PartialEq)]
+pub enum FeedbackDelivery {
+    Sent,
+    Queued,
+}
+
 #[specta::specta]
 #[tauri::command]
-pub fn feedback_window_send_feedback(
+pub async fn feedback_window_send_feedback(
     app: AppHandle,
     feedback: String,
     rating: FeedbackRate,
-    feedback_sender: State<FeedbackSenderState>,
-    settings_system: State<SettingsSystemState>,
-) -> Result<(), String> {
-    feedback_sender
-        .send_feedback(feedback, rating)
-        .map_err(|err| {
-            let msg = format!("unable to send feedback: {}", err);
-            error!("{}", msg);
-            msg
+    category: FeedbackCategory,
+    attach_diagnostics: bool,
+    crash_report_id: Option<String>,
+    feedback_sender: State<'_, FeedbackSenderState>,
+    settings_system: State<'_, SettingsSystemState>,
+) -> Result<FeedbackDelivery, ServerError> {
+    let mut attachments = Vec::new();
+    if let Some(id) = &crash_report_id {
+        match crash_reporter::report_json(id) {
+            Ok(report) => attachments.push(Attachment {
+                name: "crash_report".to_string(),
+                file_name: format!("{}.json", id),
+                content_type: "application/json".to_string(),
+                content: report.into_bytes(),
+            }),
+            Err(err) => warn!("unable to attach crash report: {:?}", err),
+        }
+    }
+    if attach_diagnostics {
+        match diagnostics::create_bundle(&app).await {
+            Ok(bundle) => attachments.push(Attachment {
+                name: "diagnostics".to_string(),
+                file_name: "diagnostics.zip".to_string(),
+                content_type: "application/zip".to_string(),
+                content: bundle,
+            }),
+            Err(err) => warn!("unable to attach diagnostics bundle: {:?}", err),
+        }
+    }
+
+    let delivery = feedback_sender
+        .submit(feedback, rating.clone(), category, attachments)
+        .await
+        .inspect_err(|err| {
+            app.alert(ErrorCode::FeedbackRejected, Some(err.clone().into()), true);
         })?;
+    if let Some(id) = crash_report_id {
+        info!("crash report {} handed over with feedback", id);
+        crash_reporter::remove(&id)
+            .unwrap_or_else(|err| warn!("unable to remove crash report: {:?}", err));
+    }
+    settings_system
+        .lock()
+        .as_mut()
+        .expect("settings_system lock required")
+        .prompt_answered(PromptKind::Feedback, PromptOutcome::Rated, Some(rating), None);
+    Ok(delivery)
+}
+
+/// Sends the 0-10 answer of the NPS survey.
+#[specta::specta]
+#[tauri::command]
+pub async fn feedback_window_send_nps(
+    score: u8,
+    comment: String,
+    feedback_sender: State<'_, FeedbackSenderState>,
+    settings_system: State<'_, SettingsSystemState>,
+) -> Result<(), ServerError> {
+    if score > 10 {
+        return Err(ServerError::BadPayload(format!("invalid NPS score {}", score)));
+    }
+    feedback_sender.send_nps(score, comment).await?;
     settings_system
         .lock()
         .as_mut()
         .expect("settings_system lock required")
-        .feedback_given(&app);
+        .prompt_answered(PromptKind::Nps, PromptOutcome::Scored, None, Some(score));
     Ok(())
 }
 
+/// The prompt, which the window should show. None, if the user opened the
+/// window.
+#[specta::specta]
+#[tauri::command]
+pub fn pending_feedback_prompt(settings_system: State<SettingsSystemState>) -> Option<PromptKind> {
+    settings_system
+        .lock()
+        .expect("settings_system lock required")
+        .pending_prompt()
+}
+
+#[specta::specta]
+#[tauri::command]
+pub fn feedback_window_dismiss_prompt(settings_system: State<SettingsSystemState>) {
+    settings_system
+        .lock()
+        .as_mut()
+        .expect("settings_system lock required")
+        .prompt_dismissed();
+}
+
 #[specta::specta]
 #[tauri::command]
 pub fn open_app_store_feedback(
@@ -124,19 +380,14 @@ pub fn open_app_store_feedback(
         .lock()
         .as_mut()
         .expect("settings_system lock required")
-        .feedback_given(&app);
+        .prompt_answered(PromptKind::Review, PromptOutcome::Reviewed, None, None);
 
     webbrowser::open_browser(
         Browser::Safari,
         "https://itunes.apple.com/app/id6743385214?action=write-review",
     )
     .unwrap_or_else(|err| {
-        app.alert(
-            "Can't open Review",
-            "Unable to open review in browser",
-            Some(err.into()),
-            true,
-        )
+        app.alert(ErrorCode::ReviewUnavailable, Some(err.into()), true)
     });
 
     Ok(())
@@ -147,16 +398,3 @@ pub fn open_app_store_feedback(
 pub fn is_full_version_and_mac() -> bool {
     return cfg!(feature = "fullversion") && cfg!(target_os = "macos");
 }
-
-pub trait FeedbackDisplay {
-    fn should_show_feedback(&self) -> bool;
-}
-
-impl FeedbackDisplay for SettingsSystem {
-    fn should_show_feedback(&self) -> bool {
-        let ask_intervals = [
-            3u32, 13u32, 21u32, 34u32, 55u32, 89u32, 100u32, 144u32, 233u32,
-        ];
-        !self.settings().feedback_provided && ask_intervals.contains(&self.settings().session_count)
-    }
-}
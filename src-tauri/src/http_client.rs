@@ -0,0 +1,179 @@
+use crate::app_config::AppConfig;
+use crate::audit_log;
+use crate::server_error::ServerError;
+use anyhow::{Context, Result};
+use log::{info, warn};
+use std::path::Path;
+use std::time::Duration;
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_http::reqwest::{Certificate, Client, Proxy, RequestBuilder, Response};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_ATTEMPTS: u32 = 3;
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// The client for all requests to our own and third party servers.
+///
+/// It is cheap to clone, all clones share the same connection pool.
+#[derive(Clone)]
+pub(crate) struct HttpClient {
+    client: Client,
+    initial_backoff: Duration,
+}
+
+impl HttpClient {
+    pub fn new<R: Runtime>(app: &AppHandle<R>, app_config: &AppConfig) -> Result<Self> {
+        let user_agent = format!(
+            "JustDrink/{} ({}; {})",
+            app.package_info().version,
+            tauri_plugin_os::platform(),
+            tauri_plugin_os::arch()
+        );
+        Self::build(&user_agent, app_config.get_https_proxy(), app_config.get_ca_bundle())
+    }
+
+    /// An invalid proxy or CA bundle is ignored, so the app still starts and the
+    /// user can reach the settings.
+    pub fn build(
+        user_agent: &str,
+        https_proxy: Option<String>,
+        ca_bundle: Option<&Path>,
+    ) -> Result<Self> {
+        let mut builder = Client::builder()
+            .user_agent(user_agent)
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(DEFAULT_TIMEOUT);
+
+        if let Some(proxy) = https_proxy {
+            match Proxy::all(&proxy) {
+                Ok(proxy_config) => {
+                    info!("use proxy {}", proxy);
+                    builder = builder.proxy(proxy_config);
+                }
+                Err(err) => warn!("ignore invalid proxy url '{}': {:?}", proxy, err),
+            }
+        }
+
+        if let Some(path) = ca_bundle {
+            match Self::read_ca_bundle(path) {
+                Ok(certificates) => {
+                    info!("use custom ca bundle {:?}", path);
+                    for certificate in certificates {
+                        builder = builder.add_root_certificate(certificate);
+                    }
+                }
+                Err(err) => warn!("ignore ca bundle: {:?}", err),
+            }
+        }
+
+        Ok(Self {
+            client: builder.build()?,
+            initial_backoff: INITIAL_BACKOFF,
+        })
+    }
+
+    fn read_ca_bundle(path: &Path) -> Result<Vec<Certificate>> {
+        let pem =
+            std::fs::read(path).with_context(|| format!("unable to read ca bundle {:?}", path))?;
+        Certificate::from_pem_bundle(&pem).with_context(|| format!("invalid ca bundle {:?}", path))
+    }
+
+    pub fn get(&self, url: &str) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    pub fn post(&self, url: &str) -> RequestBuilder {
+        self.client.post(url)
+    }
+
+    /// Sends the request once. Responses without a success status are returned
+    /// as error. The payload is recorded in the privacy audit log.
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response, ServerError> {
+        let request = request.build().map_err(ServerError::from_request)?;
+        audit_log::record_request(&request);
+        let response = self
+            .client
+            .execute(request)
+            .await
+            .map_err(ServerError::from_request)?;
+        if response.status().is_success() {
+            Ok(response)
+        } else {
+            Err(ServerError::from_response(response).await)
+        }
+    }
+
+    /// Sends a request, which can safely be repeated. Retryable errors are
+    /// retried with an exponential backoff.
+    pub async fn send_idempotent(&self, request: RequestBuilder) -> Result<Response, ServerError> {
+        let mut backoff = self.initial_backoff;
+        let mut attempt = 1;
+        loop {
+            let Some(next_request) = request.try_clone() else {
+                // streaming bodies can't be repeated
+                return self.send(request).await;
+            };
+            match self.send(next_request).await {
+                Err(err) if err.is_retryable() && attempt < MAX_ATTEMPTS => {
+                    warn!(
+                        "request failed (attempt {}/{}), retry in {:?}: {:?}",
+                        attempt, MAX_ATTEMPTS, backoff, err
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+impl HttpClient {
+    pub fn for_tests() -> Self {
+        let mut client = Self::build("JustDrink/test", None, None).unwrap();
+        client.initial_backoff = Duration::from_millis(1);
+        client
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stand_in_server::{self, StandInResponse};
+
+    #[test]
+    fn test_retry_server_failure() {
+        let url = stand_in_server::serve(vec![
+            StandInResponse::status("503 Service Unavailable"),
+            StandInResponse::status("200 OK"),
+        ]);
+        let client = HttpClient::for_tests();
+        let response =
+            tauri::async_runtime::block_on(client.send_idempotent(client.get(&url))).unwrap();
+        assert!(response.status().is_success());
+    }
+
+    #[test]
+    fn test_no_retry_for_rejected_request() {
+        let url = stand_in_server::serve(vec![
+            StandInResponse::status("400 Bad Request"),
+            StandInResponse::status("200 OK"),
+        ]);
+        let client = HttpClient::for_tests();
+        let result = tauri::async_runtime::block_on(client.send_idempotent(client.get(&url)));
+        assert!(matches!(result, Err(ServerError::Rejected { status: 400, .. })));
+    }
+
+    #[test]
+    fn test_ignore_invalid_network_settings() {
+        let client = HttpClient::build(
+            "JustDrink/test",
+            Some("not a proxy url".to_string()),
+            Some(Path::new("/does/not/exist.pem")),
+        );
+        assert!(client.is_ok());
+    }
+}
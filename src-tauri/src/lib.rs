@@ -1,15 +1,35 @@
 mod alert;
+mod alert_queue;
+mod analytics_sink;
+mod audit_log;
+mod autostart;
+mod checkout_watcher;
+mod consent;
 mod countdown_timer;
+mod crash_reporter;
+mod data_eraser;
 mod detect_idling;
+mod diagnostics;
+mod error_catalog;
+mod feedback_queue;
+mod http_client;
 mod model;
+mod multipart;
+mod persistence;
+mod price;
 mod pretty_time;
+mod prompt_policy;
 mod tracking;
+mod tracking_queue;
 mod tray;
+mod update_manager;
 
 mod app_config;
 mod dashboard_window;
 mod feedback_window;
+mod license_cache;
 mod license_manager;
+mod license_scheduler;
 mod session_window;
 mod settings_manager;
 mod settings_system;
@@ -17,29 +37,42 @@ mod settings_window;
 mod subscription_manager;
 mod updater_window;
 mod welcome_window;
+mod server_error;
 mod setup;
+#[cfg(test)]
+mod stand_in_server;
 
 use log::{info};
-use serde_json::json;
+use serde_json::{json, Value};
 #[cfg(debug_assertions)]
 use specta_typescript::Typescript;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 #[cfg(target_os = "macos")]
 use tauri::ActivationPolicy;
 
 use crate::countdown_timer::CountdownTimer;
 
 use crate::alert::Alert;
+use crate::app_config::{AnalyticsSinkKind, AppConfig};
+use crate::consent::TelemetryKind;
+use crate::error_catalog::ErrorCode;
+use crate::persistence::Persistence;
 use crate::settings_manager::SettingsManager;
 use crate::settings_system::SettingsSystem;
 use crate::tracking::Tracking;
+use tauri::plugin::TauriPlugin;
 use tauri::{AppHandle, Manager, RunEvent, WindowEvent};
-use tauri_plugin_aptabase::EventTracker;
 use tauri_plugin_autostart::MacosLauncher;
 use tauri_plugin_log::Target;
 use tauri_specta::{collect_commands, collect_events, Builder, Commands, ErrorHandlingMode, Events};
 
+type PersistenceState = Arc<Persistence>;
+type AutostartState = autostart::Autostart;
+type CheckoutWatcherState = checkout_watcher::CheckoutWatcher;
+type DataEraserState = data_eraser::DataEraser;
 type FeedbackSenderState = feedback_window::FeedbackSender;
+type PromptPolicyState = prompt_policy::PromptPolicyManager;
+type UpdateManagerState = update_manager::UpdateManager;
 type SettingsManagerState = SettingsManager;
 type SettingsSystemState = Mutex<SettingsSystem>;
 type CountdownTimerState = CountdownTimer;
@@ -58,6 +91,11 @@ pub fn run() {
             feedback_window::feedback_window_send_feedback,
             feedback_window::is_full_version_and_mac,
             feedback_window::open_app_store_feedback,
+            feedback_window::feedback_window_send_nps,
+            feedback_window::pending_feedback_prompt,
+            feedback_window::feedback_window_dismiss_prompt,
+            crash_reporter::pending_crash_report,
+            crash_reporter::dismiss_crash_report,
             session_window::start_session,
             session_window::end_session,
             settings_window::open_settings,
@@ -65,6 +103,12 @@ pub fn run() {
             settings_window::get_device_id,
             settings_window::update_settings,
             settings_window::open_browser,
+            consent::get_telemetry_consent,
+            consent::set_telemetry_consent,
+            audit_log::privacy_audit_log,
+            data_eraser::delete_my_data,
+            data_eraser::restart_after_data_deletion,
+            diagnostics::create_diagnostics_bundle,
             welcome_window::welcome_with,
             welcome_window::welcome_load_settings,
             welcome_window::welcome_redo,
@@ -73,7 +117,18 @@ pub fn run() {
             welcome_window::open_payment,
             alert::close_error_window,
             updater_window::updater_close,
+            updater_window::updater_info,
+            updater_window::updater_install,
+            updater_window::updater_skip_version,
+            updater_window::updater_remind_later,
+            updater_window::updater_rollback,
             license_manager::request_license_status,
+            license_manager::activate_license_key,
+            license_manager::deactivate_license_key,
+            license_manager::license_devices,
+            license_manager::cancel_payment,
+            license_manager::current_license_status,
+            license_manager::redeem_code,
 
         ],
         collect_events![
@@ -82,48 +137,36 @@ pub fn run() {
             model::settings::WelcomeWizardMode,
             model::settings::SettingsUserDetails,
             license_manager::LicenseResult,
+            model::license::LicenseChanged,
             countdown_timer::CountdownEvent,
             countdown_timer::TimerStatus,
+            update_manager::UpdateProgress,
+            update_manager::UpdateReady,
         ],
     )
     .unwrap();
 
     tauri::Builder::default()
         .plugin(tauri_plugin_process::init())
-        .plugin(tauri_plugin_single_instance::init(|_app, _args, _cwd| {
+        .plugin(tauri_plugin_single_instance::init(|app, args, _cwd| {
+            diagnostics::handle_args(app, &args);
             info!("open dashboard - only on windows");
             #[cfg(target_os = "windows")]
             {
                 info!("instance of Just Drink! already open");
-                show_dashboard(_app);
+                show_dashboard(app);
             }
         }))
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_http::init())
-        .plugin(tauri_plugin_store::Builder::default().build())
         .plugin(tauri_plugin_os::init())
+        .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_autostart::init(
             MacosLauncher::LaunchAgent,
             Some(vec!["--quiet"]),
         ))
-        .plugin(
-            tauri_plugin_aptabase::Builder::new("A-EU-1597927502")
-                .with_panic_hook(Box::new(|client, info, msg| {
-                    info!("panic detection");
-                    let location = info
-                        .location()
-                        .map(|loc| format!("{}:{}:{}", loc.file(), loc.line(), loc.column()))
-                        .unwrap_or_else(|| "".to_string());
-                    client.track_event(
-                        "panic",
-                        Some(json!({
-                            "info": format!("{} ({})", msg, location),
-                        })),
-                    );
-                }))
-                .build(),
-        )
+        .plugin(aptabase_plugin())
         .plugin(
             tauri_plugin_log::Builder::default()
                 .targets([
@@ -149,6 +192,10 @@ pub fn run() {
         })
         .on_window_event(|window, event| match event {
             WindowEvent::CloseRequested { api, .. } => {
+                // a closed alert is destroyed, which shows the next queued alert
+                if window.label() == alert::WINDOW_LABEL {
+                    return;
+                }
                 #[cfg(target_os = "macos")]
                 window
                     .app_handle()
@@ -174,23 +221,50 @@ pub fn run() {
             }
             RunEvent::ExitRequested { .. } => {
                 info!("Closing Just Drink! Stop timer.");
-                app.track_event("app_quit", None);
+                tracking::track_quit(app);
                 let timer = app.state::<CountdownTimerState>();
                 timer.stop();
+                if updater_window::install_downloaded(app) {
+                    info!("installed the downloaded update on quit");
+                }
             }
             _ => {}
         })
 }
 
+/// Panics are reported by the Aptabase plugin, as long as it is a configured
+/// analytics sink and the user opted in to crash reports.
+fn aptabase_plugin() -> TauriPlugin<tauri::Wry> {
+    let builder = tauri_plugin_aptabase::Builder::new("A-EU-1597927502");
+    if !AppConfig::analytics_sinks().contains(&AnalyticsSinkKind::Aptabase) {
+        return builder.build();
+    }
+    builder
+        .with_panic_hook(Box::new(|client, info, msg| {
+            info!("panic detection");
+            if !consent::allows(TelemetryKind::CrashReports) {
+                return;
+            }
+            let location = info
+                .location()
+                .map(|loc| format!("{}:{}:{}", loc.file(), loc.line(), loc.column()))
+                .unwrap_or_else(|| "".to_string());
+            let event = tracking::Event::Panic {
+                info: format!("{} ({})", msg, location),
+            };
+            let properties = Value::Object(event.properties());
+            audit_log::record_json(
+                "aptabase",
+                &json!({"name": event.name(), "props": properties}),
+            );
+            client.track_event(&event.name(), Some(properties));
+        }))
+        .build()
+}
 
 fn show_dashboard(app: &AppHandle) {
     dashboard_window::show(app.app_handle()).unwrap_or_else(|err| {
-        app.alert(
-            "Can't open action menu",
-            "Action Menu can't be opened during new instance. Please try again later.",
-            Some(err),
-            false,
-        );
+        app.alert(ErrorCode::ActionMenuUnavailable, Some(err), false);
     });
 }
 
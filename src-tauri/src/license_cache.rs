@@ -0,0 +1,135 @@
+use crate::app_config::LOCAL_API_URL;
+use crate::persistence::Persisted;
+use anyhow::{anyhow, Result};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use chrono::{DateTime, Duration, Utc};
+use minisign_verify::{PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+
+pub(crate) const SIGNATURE_HEADER: &str = "x-license-signature";
+
+pub(crate) const LOCAL_PUBLIC_KEY: &str = include_str!("../keys/license-local.pub");
+const CLOCK_SKEW_MINUTES: i64 = 5;
+
+/// The last license response, which could be verified by the server signature.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct LicenseCache {
+    pub(crate) body: String,
+    pub(crate) signature: String,
+}
+
+impl Persisted for LicenseCache {
+    const FILE_NAME: &'static str = "mm-license-cache.json";
+    const ROOT_KEY: &'static str = "license";
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct VerifiedLicense {
+    pub(crate) body: String,
+    pub(crate) validated_at: DateTime<Utc>,
+}
+
+/// The key of the license server. Release builds get it from the
+/// `JUSTDRINK_LICENSE_PUBLIC_KEY` variable during compile time, debug builds
+/// without it trust the key of the local license server, while they talk to it.
+pub(crate) fn public_key(api_url: &str) -> Option<&'static str> {
+    match option_env!("JUSTDRINK_LICENSE_PUBLIC_KEY") {
+        Some(public_key) => Some(public_key),
+        None if cfg!(debug_assertions) && api_url == LOCAL_API_URL => Some(LOCAL_PUBLIC_KEY),
+        None => None,
+    }
+}
+
+impl LicenseCache {
+    /// Verifies the signature and returns the signed validation time. The
+    /// signature must have been issued for this device.
+    pub fn verify(&self, public_key: &str, device_id: &str) -> Result<VerifiedLicense> {
+        let public_key = PublicKey::decode(public_key)
+            .or_else(|_| PublicKey::from_base64(public_key.trim()))
+            .map_err(|err| anyhow!("invalid license public key: {}", err))?;
+        let signature = Signature::decode(&String::from_utf8(
+            BASE64_STANDARD.decode(self.signature.trim())?,
+        )?)
+        .map_err(|err| anyhow!("invalid license signature: {}", err))?;
+
+        public_key
+            .verify(self.body.as_bytes(), &signature, false)
+            .map_err(|err| anyhow!("license signature does not match: {}", err))?;
+
+        let mut timestamp = None;
+        let mut signed_device_id = None;
+        for entry in signature.trusted_comment().split('\t') {
+            match entry.split_once(':') {
+                Some(("timestamp", value)) => timestamp = Some(value.trim().parse::<i64>()?),
+                Some(("device", value)) => signed_device_id = Some(value.trim()),
+                _ => {}
+            }
+        }
+
+        if signed_device_id != Some(device_id) {
+            return Err(anyhow!("license was signed for another device"));
+        }
+
+        let validated_at = timestamp
+            .and_then(|secs| DateTime::<Utc>::from_timestamp(secs, 0))
+            .ok_or_else(|| anyhow!("license signature has no timestamp"))?;
+
+        Ok(VerifiedLicense {
+            body: self.body.clone(),
+            validated_at,
+        })
+    }
+}
+
+impl VerifiedLicense {
+    pub fn within_grace_period(&self, now: DateTime<Utc>, grace_period: Duration) -> bool {
+        self.validated_at <= now + Duration::minutes(CLOCK_SKEW_MINUTES)
+            && now - self.validated_at <= grace_period
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEVICE_ID: &str = "babe078dea80f69a44f3bec6ab9696b2c9a22b96172d23501b6aeb12cdf38053";
+
+    fn paid_cache() -> LicenseCache {
+        LicenseCache {
+            body: include_str!("../tests/fixtures/license/active_paid.json").to_string(),
+            signature: include_str!("../tests/fixtures/license/active_paid.json.sig").to_string(),
+        }
+    }
+
+    #[test]
+    fn test_verify_signed_license() {
+        let license = paid_cache().verify(LOCAL_PUBLIC_KEY, DEVICE_ID).unwrap();
+        assert_eq!(
+            license.validated_at,
+            "2026-10-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_reject_modified_license() {
+        let mut cache = paid_cache();
+        cache.body = cache.body.replace("ACTIVE_PAID", "ACTIVE_APPLE_APP_STORE");
+        assert!(cache.verify(LOCAL_PUBLIC_KEY, DEVICE_ID).is_err());
+    }
+
+    #[test]
+    fn test_reject_license_of_other_device() {
+        assert!(paid_cache().verify(LOCAL_PUBLIC_KEY, "other-device").is_err());
+    }
+
+    #[test]
+    fn test_grace_period() {
+        let license = paid_cache().verify(LOCAL_PUBLIC_KEY, DEVICE_ID).unwrap();
+        let grace_period = Duration::days(7);
+        let validated_at = license.validated_at;
+
+        assert!(license.within_grace_period(validated_at + Duration::days(6), grace_period));
+        assert!(!license.within_grace_period(validated_at + Duration::days(8), grace_period));
+        assert!(!license.within_grace_period(validated_at - Duration::days(1), grace_period));
+    }
+}
@@ -1,30 +1,36 @@
 use crate::alert::Alert;
 use crate::app_config::AppConfig;
+use crate::error_catalog::ErrorCode;
+use crate::license_cache::{LicenseCache, SIGNATURE_HEADER};
 use crate::license_manager::response::PaymentStatus;
-use crate::model::license::LicenseInfoStatus;
-use crate::{model, LicenseManagerState};
-use chrono::Utc;
-use log::{info};
+use crate::model::license::{LicenseDevice, LicenseInfoStatus, LicenseKeyError, RedeemCodeError};
+use crate::http_client::HttpClient;
+use crate::persistence::Persistence;
+use crate::server_error::ServerError;
+use crate::{license_cache, license_scheduler, model, price, LicenseManagerState, SettingsSystemState};
+use chrono::{DateTime, Utc};
+use log::{info, warn};
 use serde::{Deserialize, Serialize};
 use specta::Type;
-use std::error::Error;
 use std::sync::Arc;
+use std::time::Duration;
 use tauri::async_runtime::{Mutex};
-use tauri::http::StatusCode;
 use tauri::{AppHandle, Manager, State};
-use tauri_plugin_http::reqwest::{Client, Response};
+use tauri_plugin_http::reqwest::{RequestBuilder, Response};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
 
 mod response {
+    use crate::price::ServerPrice;
     use chrono::{DateTime, Utc};
     use serde::Deserialize;
 
-    #[allow(dead_code)]
     #[derive(Deserialize, Debug, Clone)]
-    pub(crate) struct ErrorResponse {
-        status: u16,
-        pub error: String,
-        pub message: String,
-        path: String,
+    pub(crate) struct Device {
+        #[serde(rename(deserialize = "deviceId"))]
+        pub(crate) device_id: String,
+        #[serde(rename(deserialize = "activatedAt"))]
+        pub(crate) activated_at: DateTime<Utc>,
     }
 
     #[derive(Deserialize, Debug, Clone)]
@@ -46,7 +52,10 @@ mod response {
         #[serde(rename(deserialize = "trialDaysLeft"))]
         pub(crate) trial_days_left: u32,
         #[serde(rename(deserialize = "purchasePrice"))]
-        pub(crate) purchase_price: f64,
+        pub(crate) purchase_price: ServerPrice,
+        /// The price without a redeemed discount.
+        #[serde(rename(deserialize = "regularPrice"), default)]
+        pub(crate) regular_price: Option<ServerPrice>,
         #[serde(rename(deserialize = "paymentStatus"))]
         pub(crate) payment_status: PaymentStatus,
     }
@@ -112,6 +121,7 @@ pub enum LicenseStatus {
     Valid(ValidTypes),
     Expired(String),
     Invalid(String),
+    Unknown(String),
 }
 
 #[allow(dead_code)]
@@ -119,6 +129,7 @@ pub enum LicenseStatus {
 pub struct LicenseData {
     pub status: LicenseStatus,
     pub purchase_info: response::PurchaseInfo,
+    pub offline_since: Option<DateTime<Utc>>,
 }
 
 impl LicenseStatus {
@@ -127,6 +138,7 @@ impl LicenseStatus {
             LicenseStatus::Valid(_) => true,
             LicenseStatus::Expired(_) => false,
             LicenseStatus::Invalid(_) => false,
+            LicenseStatus::Unknown(_) => false,
         }
     }
 }
@@ -175,13 +187,35 @@ impl LicenseData {
                 license_key: None,
                 message: Some(error.clone()),
             },
+            LicenseStatus::Unknown(reason) => model::license::LicenseInfo {
+                status: LicenseInfoStatus::Unknown,
+                license_key: None,
+                message: Some(reason.clone()),
+            },
+        };
+        let info = match (self.offline_since, &self.status) {
+            (Some(validated_at), LicenseStatus::Valid(_)) => model::license::LicenseInfo {
+                message: Some(format!(
+                    "{} (offline, last verified {})",
+                    info.message.unwrap_or_default(),
+                    validated_at.format("%Y-%m-%d")
+                )),
+                ..info
+            },
+            _ => info,
         };
+        let locale = price::system_locale();
         model::license::LicenseData {
             info,
             payment: model::license::LicensePaymentInfo {
                 total_trail_days: self.purchase_info.total_trial_days,
                 trial_days_left: self.purchase_info.trial_days_left,
-                purchase_price: self.purchase_info.purchase_price,
+                purchase_price: self.purchase_info.purchase_price.to_model(&locale),
+                regular_price: self
+                    .purchase_info
+                    .regular_price
+                    .as_ref()
+                    .map(|price| price.to_model(&locale)),
                 payment_status: self.purchase_info.payment_status.to_model(),
             },
         }
@@ -189,56 +223,114 @@ impl LicenseData {
 }
 
 pub struct LicenseManager {
-    client: Client,
+    client: HttpClient,
     pub device_id: model::device::DeviceId,
     status: Arc<Mutex<Option<LicenseData>>>,
+    persistence: Arc<Persistence>,
+    grace_period: chrono::Duration,
+    /// Verifies the license responses and the cached license, none if this
+    /// build has no key.
+    public_key: Option<&'static str>,
 }
 
-#[allow(dead_code)]
-#[derive(Debug)]
-enum ServerRequestError {
-    Other(anyhow::Error), // For other errors (e.g., non-200, non-400 responses)
+impl From<ServerError> for LicenseKeyError {
+    fn from(err: ServerError) -> Self {
+        match err.rejected_code() {
+            Some("LICENSE_NOT_FOUND") | Some("INVALID_LICENSE_KEY") => {
+                LicenseKeyError::InvalidLicenseKey
+            }
+            Some("SEAT_LIMIT_REACHED") => LicenseKeyError::SeatLimitReached,
+            Some("DEVICE_NOT_ACTIVATED") => LicenseKeyError::DeviceNotActivated,
+            _ => LicenseKeyError::Server(err),
+        }
+    }
 }
 
-impl LicenseManager {
-    pub fn new(device_id: &model::device::DeviceId) -> Self {
-        let client = Client::new();
+impl From<ServerError> for RedeemCodeError {
+    fn from(err: ServerError) -> Self {
+        match err.rejected_code() {
+            Some("CODE_EXPIRED") => RedeemCodeError::Expired,
+            Some("CODE_ALREADY_USED") => RedeemCodeError::AlreadyUsed,
+            Some("CODE_NOT_FOUND") | Some("INVALID_CODE") => RedeemCodeError::Invalid,
+            _ => RedeemCodeError::Server(err),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct RedeemCodeRequest {
+    code: String,
+    #[serde(rename = "deviceId")]
+    device_id: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct LicenseKeyRequest {
+    #[serde(rename = "licenseKey")]
+    license_key: String,
+    #[serde(rename = "deviceId")]
+    device_id: String,
+}
 
+impl LicenseManager {
+    pub fn new(
+        device_id: &model::device::DeviceId,
+        client: HttpClient,
+        persistence: Arc<Persistence>,
+        app_config: &AppConfig,
+    ) -> Self {
         info!("LicenseManager started.");
         Self {
             client,
             device_id: device_id.clone(),
             status: Arc::new(Mutex::new(None)),
+            persistence,
+            grace_period: app_config.get_license_grace_period(),
+            public_key: license_cache::public_key(&app_config.get_url()),
         }
     }
 
     async fn validate(
-        client: &Client,
-        device_id: &model::device::DeviceId,
-    ) -> Result<LicenseData, ServerRequestError> {
+        &self,
+        base_url: &str,
+    ) -> Result<(LicenseData, Option<LicenseCache>), ServerError> {
         info!("validating license");
         let url = format!(
             "{}/app/v1/license/validate?device-id={}",
-            AppConfig::build().get_url(),
-            device_id.get_hash_hex_id()
+            base_url,
+            self.device_id.get_hash_hex_id()
         );
 
-        let response = client
-            .post(&url)
-            .header("origin", Self::origin())
-            .body("")
-            .send()
+        let response = self
+            .client
+            .send_idempotent(
+                self.client
+                    .post(&url)
+                    .header("origin", Self::origin())
+                    .timeout(REQUEST_TIMEOUT)
+                    .body(""),
+            )
             .await
-            .map_err(|err| {
-                ServerRequestError::Other(anyhow::anyhow!(
-                    "status: {:?}, source: {:?}, url: {:?}",
-                    err.status(),
-                    err.source(),
-                    err.url()
-                ))
-            })?;
+            .inspect_err(|err| warn!("license request to {:?} failed: {:?}", url, err))?;
 
-        Self::parse_response(&url, response).await
+        self.parse_response(&url, response, Utc::now()).await
+    }
+
+    fn license_key_request(
+        &self,
+        path: &str,
+        license_key: &str,
+        device_id: Option<String>,
+    ) -> RequestBuilder {
+        let url = format!("{}/app/v1/license/{}", AppConfig::build().get_url(), path);
+        self.client
+            .post(&url)
+            .header("origin", Self::origin())
+            .timeout(REQUEST_TIMEOUT)
+            .json(&LicenseKeyRequest {
+                license_key: license_key.to_string(),
+                device_id: device_id.unwrap_or_else(|| self.device_id.get_hash_hex_id()),
+            })
     }
 
     fn origin() -> String {
@@ -250,36 +342,72 @@ impl LicenseManager {
     }
 
     async fn parse_response(
-        url: &String,
+        &self,
+        url: &str,
         response: Response,
-    ) -> Result<LicenseData, ServerRequestError> {
-        match response.status() {
-            StatusCode::OK => Self::parse_json(response).await.map_err(|err| {
-                ServerRequestError::Other(anyhow::anyhow!(
-                    "failed to parse response from url '{:?}': {:?}",
-                    url,
-                    err
-                ))
-            }),
-            _ => Err(ServerRequestError::Other(anyhow::anyhow!(
-                "failed license request, unknown error with: {:?}, url: {:?}",
-                response.status(),
-                response.url().path()
-            ))),
+        now: DateTime<Utc>,
+    ) -> Result<(LicenseData, Option<LicenseCache>), ServerError> {
+        let signature = response
+            .headers()
+            .get(SIGNATURE_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+        let body = response.text().await.map_err(ServerError::from_request)?;
+        let license_data = Self::parse_json(&body)
+            .inspect_err(|err| warn!("failed to parse response from url '{:?}': {:?}", url, err))?;
+        let cache = self
+            .verify_response(&license_data, body, signature, now)
+            .inspect_err(|err| warn!("untrusted response from url '{:?}': {:?}", url, err))?;
+        Ok((license_data, cache))
+    }
+
+    /// An active license is only trusted with a recent signature of the
+    /// license server for this device. Returns the response to cache, if it is
+    /// signed.
+    fn verify_response(
+        &self,
+        license_data: &LicenseData,
+        body: String,
+        signature: Option<String>,
+        now: DateTime<Utc>,
+    ) -> Result<Option<LicenseCache>, ServerError> {
+        let cache = signature.map(|signature| LicenseCache { body, signature });
+        let verified = match (&cache, self.public_key) {
+            (Some(cache), Some(public_key)) => cache
+                .verify(public_key, &self.device_id.get_hash_hex_id())
+                .and_then(|license| {
+                    if license.within_grace_period(now, self.grace_period) {
+                        Ok(())
+                    } else {
+                        Err(anyhow::anyhow!("license signature is outdated"))
+                    }
+                }),
+            (Some(_), None) => Err(anyhow::anyhow!("no public key to verify the license")),
+            (None, _) => Err(anyhow::anyhow!("license response is not signed")),
+        };
+        match verified {
+            Ok(()) => Ok(cache),
+            // only debug builds may talk to a license server without a key
+            Err(err) if self.public_key.is_none() && cfg!(debug_assertions) => {
+                warn!("trust unverified license response: {:?}", err);
+                Ok(None)
+            }
+            Err(err) if license_data.status.is_active() => Err(ServerError::bad_payload(err)),
+            Err(err) => {
+                info!("license response isn't cached: {:?}", err);
+                Ok(None)
+            }
         }
     }
 
-    async fn parse_json(response: Response) -> Result<LicenseData, ServerRequestError> {
-        let response: response::Response = response.json().await.map_err(|err| {
-            ServerRequestError::Other(anyhow::anyhow!("failed to parse response: {:?}", err))
-        })?;
+    fn parse_json(body: &str) -> Result<LicenseData, ServerError> {
+        let response: response::Response =
+            serde_json::from_str(body).map_err(ServerError::bad_payload)?;
 
         let license_status = match response.status {
             response::LicenseStatus::ActiveTrial => {
                 let trial = response.trial.ok_or_else(|| {
-                    ServerRequestError::Other(anyhow::anyhow!(
-                        "marked as active trial, but no details not found"
-                    ))
+                    ServerError::bad_payload("marked as active trial, but no details not found")
                 })?;
                 LicenseStatus::Valid(ValidTypes::Trial(TrialDetails {
                     expired_at: trial.expires_at,
@@ -287,9 +415,7 @@ impl LicenseManager {
             }
             response::LicenseStatus::ActivePaid => {
                 let paid = response.paid.ok_or_else(|| {
-                    ServerRequestError::Other(anyhow::anyhow!(
-                        "marked as active paid, but no details not found"
-                    ))
+                    ServerError::bad_payload("marked as active paid, but no details not found")
                 })?;
                 LicenseStatus::Valid(ValidTypes::Paid(PaidDetails {
                     license_key: paid.license_key,
@@ -311,9 +437,42 @@ impl LicenseManager {
         Ok(LicenseData {
             status: license_status,
             purchase_info: response.purchase_info,
+            offline_since: None,
         })
     }
 
+    /// Reads the last signed license response. Trials still expire, while a
+    /// valid license becomes unknown, once the grace period has passed.
+    fn cached_license_data(&self, now: DateTime<Utc>) -> Result<LicenseData, anyhow::Error> {
+        let public_key = self
+            .public_key
+            .ok_or_else(|| anyhow::anyhow!("no public key to verify the license cache"))?;
+        let license = self
+            .persistence
+            .load::<LicenseCache>()?
+            .ok_or_else(|| anyhow::anyhow!("no cached license available"))?
+            .verify(public_key, &self.device_id.get_hash_hex_id())?;
+
+        let mut license_data = Self::parse_json(&license.body)?;
+        license_data.offline_since = Some(license.validated_at);
+
+        if let LicenseStatus::Valid(ValidTypes::Trial(trial)) = &license_data.status {
+            if trial.expired_at < now {
+                license_data.status = LicenseStatus::Expired("You trial expired".to_string());
+            }
+        }
+
+        if license_data.status.is_active() && !license.within_grace_period(now, self.grace_period)
+        {
+            license_data.status = LicenseStatus::Unknown(format!(
+                "Your license could not be verified since {}",
+                license.validated_at.format("%Y-%m-%d")
+            ));
+        }
+
+        Ok(license_data)
+    }
+
     pub async fn get_status(
         &self,
         app_handle: &AppHandle,
@@ -323,7 +482,10 @@ impl LicenseManager {
         let do_request = self.status.lock().await.is_none() || force_request;
 
         if do_request && !prevent_server_request {
-            self.refresh_license_status(app_handle).await?;
+            return self
+                .refresh_license_status(app_handle, false)
+                .await
+                .map_err(|err| err.to_string());
         }
 
         let status = self.status.lock().await;
@@ -333,57 +495,204 @@ impl LicenseManager {
         }
     }
 
+    /// Validates the license, the cached license is used while the server isn't
+    /// reachable. `silent` errors are only logged.
     pub async fn refresh_license_status(
         &self,
         app_handle: &AppHandle,
-    ) -> Result<LicenseData, String> {
+        silent: bool,
+    ) -> Result<LicenseData, ServerError> {
 
         // do not request, if status is already paid or full
         {
             let status = self.status.lock().await;
             if let Some(data) = status.as_ref() {
-                if matches!(
-                    data.status,
-                    LicenseStatus::Valid(ValidTypes::Paid(_))
-                        | LicenseStatus::Valid(ValidTypes::Full)
-                ) {
+                if data.offline_since.is_none()
+                    && matches!(
+                        data.status,
+                        LicenseStatus::Valid(ValidTypes::Paid(_))
+                            | LicenseStatus::Valid(ValidTypes::Full)
+                    )
+                {
                     return Ok(data.clone());
                 }
             }
         }
 
-        match Self::validate(&self.client, &self.device_id).await {
-            Ok(license_data) => {
-                // Acquire a *mutable* lock on status again to update it
-                let mut status = self.status.lock().await;
-                *status = Some(license_data.clone());
-                Ok(license_data)
-            }
-            Err(err) => match err {
-                ServerRequestError::Other(e) => {
-                    app_handle.alert(
-                        "License Error",
-                        "Unable to access the license server. Please try again later.",
-                        Some(e),
-                        true,
-                    );
-                    Err("Unable to access the license server. Please try again later.".to_string())
+        match self.validate(&AppConfig::build().get_url()).await {
+            Ok((license_data, cache)) => Ok(self.store(license_data, cache).await),
+            // proxies, captive portals and TLS inspection don't reject the license
+            Err(err) if !err.is_authoritative() => {
+                match self.cached_license_data(Utc::now()) {
+                    Ok(license_data) => {
+                        warn!(
+                            "license server not reachable, use cached license: {:?}",
+                            err
+                        );
+                        return Ok(self.store(license_data, None).await);
+                    }
+                    Err(cache_err) => {
+                        info!("no usable cached license: {:?}", cache_err)
+                    }
                 }
-            },
+
+                // retrying won't help for the others, so the user needs to know about it
+                app_handle.alert(
+                    ErrorCode::LicenseServerUnreachable,
+                    Some(err.clone().into()),
+                    silent || err.is_retryable(),
+                );
+                Err(err)
+            }
+            Err(err) => {
+                app_handle.alert_with_detail(
+                    ErrorCode::LicenseRejected,
+                    Some(err.to_string()),
+                    Some(err.clone().into()),
+                    silent,
+                );
+                Err(err)
+            }
+        }
+    }
+
+    /// Validates the license once, without falling back to the cached license.
+    /// It is polled during a checkout, so the license is only stored, if it
+    /// changed.
+    pub async fn poll_license_status(&self) -> Result<LicenseData, ServerError> {
+        let (license_data, cache) = self.validate(&AppConfig::build().get_url()).await?;
+        let changed = self.status.lock().await.as_ref().map(LicenseData::to_model)
+            != Some(license_data.to_model());
+        if !changed {
+            return Ok(license_data);
+        }
+        Ok(self.store(license_data, cache).await)
+    }
+
+    async fn store(&self, license_data: LicenseData, cache: Option<LicenseCache>) -> LicenseData {
+        if let Some(cache) = cache {
+            self.persistence
+                .save(&cache)
+                .unwrap_or_else(|err| warn!("unable to cache license: {:?}", err));
         }
+        // Acquire a *mutable* lock on status again to update it
+        let mut status = self.status.lock().await;
+        *status = Some(license_data.clone());
+        license_data
+    }
+
+    /// Binds an existing license key to this device.
+    pub async fn activate(&self, license_key: &str) -> Result<LicenseData, LicenseKeyError> {
+        info!("activate license key on this device");
+        let response = self
+            .client
+            .send(self.license_key_request("activate", license_key, None))
+            .await
+            .inspect_err(|err| warn!("license activation failed: {:?}", err))?;
+        let url = response.url().to_string();
+        let (license_data, cache) = self.parse_response(&url, response, Utc::now()).await?;
+        Ok(self.store(license_data, cache).await)
+    }
+
+    /// Releases the seat of a device. Without a device id, this device is released.
+    pub async fn deactivate(
+        &self,
+        license_key: &str,
+        device_id: Option<String>,
+    ) -> Result<(), LicenseKeyError> {
+        let this_device = device_id
+            .as_ref()
+            .is_none_or(|id| *id == self.device_id.get_hash_hex_id());
+        info!("deactivate license key, this device: {this_device}");
+
+        self.client
+            .send(self.license_key_request("deactivate", license_key, device_id))
+            .await
+            .inspect_err(|err| warn!("license deactivation failed: {:?}", err))?;
+
+        if this_device {
+            self.persistence
+                .delete::<LicenseCache>()
+                .unwrap_or_else(|err| warn!("unable to remove cached license: {:?}", err));
+            *self.status.lock().await = None;
+        }
+        Ok(())
+    }
+
+    /// Applies a promo or referral code. The server answers with the updated
+    /// license, e.g. with a discounted price or a longer trial.
+    pub async fn redeem(&self, code: &str) -> Result<LicenseData, RedeemCodeError> {
+        info!("redeem code");
+        let url = format!("{}/app/v1/license/redeem", AppConfig::build().get_url());
+        let request = self
+            .client
+            .post(&url)
+            .header("origin", Self::origin())
+            .timeout(REQUEST_TIMEOUT)
+            .json(&RedeemCodeRequest {
+                code: code.to_string(),
+                device_id: self.device_id.get_hash_hex_id(),
+            });
+        let response = self
+            .client
+            .send(request)
+            .await
+            .inspect_err(|err| warn!("unable to redeem code: {:?}", err))?;
+        let (license_data, cache) = self.parse_response(&url, response, Utc::now()).await?;
+        Ok(self.store(license_data, cache).await)
+    }
+
+    /// Cancels the open checkout of this device.
+    pub async fn cancel_payment(&self) -> Result<(), ServerError> {
+        let url = format!(
+            "{}/app/v1/payment/cancel/{}",
+            AppConfig::build().get_url(),
+            self.device_id.get_hash_hex_id()
+        );
+        info!("cancel payment");
+        self.client
+            .send(self.client.post(&url).timeout(REQUEST_TIMEOUT))
+            .await
+            .inspect_err(|err| warn!("unable to cancel payment: {:?}", err))?;
+        Ok(())
+    }
+
+    pub async fn devices(&self, license_key: &str) -> Result<Vec<LicenseDevice>, LicenseKeyError> {
+        let response = self
+            .client
+            .send_idempotent(self.license_key_request("devices", license_key, None))
+            .await
+            .inspect_err(|err| warn!("loading license devices failed: {:?}", err))?;
+        let devices: Vec<response::Device> =
+            response.json().await.map_err(ServerError::from_request)?;
+
+        let current_device_id = self.device_id.get_hash_hex_id();
+        Ok(devices
+            .into_iter()
+            .map(|device| LicenseDevice {
+                current: device.device_id == current_device_id,
+                device_id: device.device_id,
+                activated_at: device.activated_at.to_rfc3339(),
+            })
+            .collect())
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Type, tauri_specta::Event)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Type, tauri_specta::Event)]
 pub enum LicenseResultStatus {
+    Pending,
     Success,
+    Canceled,
+    TimedOut,
     Error,
 }
 
+/// Progress of a checkout, which was opened in the browser.
 #[derive(Serialize, Deserialize, Debug, Clone, Type, tauri_specta::Event)]
 pub struct LicenseResult {
-    status: LicenseResultStatus,
-    error: Option<String>,
+    pub(crate) status: LicenseResultStatus,
+    pub(crate) payment_status: Option<model::license::LicensePaymentStatus>,
+    pub(crate) error: Option<String>,
 }
 
 #[specta::specta]
@@ -391,9 +700,249 @@ pub struct LicenseResult {
 pub async fn request_license_status(
     app: AppHandle,
     license_manager: State<'_, LicenseManagerState>,
-) -> Result<model::license::LicenseData, String> {
+) -> Result<model::license::LicenseData, ServerError> {
     license_manager
-        .refresh_license_status(app.app_handle())
+        .refresh_license_status(app.app_handle(), false)
         .await
         .map(|data| data.to_model())
 }
+
+#[specta::specta]
+#[tauri::command]
+pub async fn activate_license_key(
+    app: AppHandle,
+    license_key: String,
+    license_manager: State<'_, LicenseManagerState>,
+) -> Result<model::license::LicenseData, LicenseKeyError> {
+    let license = license_manager
+        .activate(license_key.trim())
+        .await
+        .map(|data| data.to_model())?;
+    license_scheduler::publish(&app, license.clone());
+    Ok(license)
+}
+
+#[specta::specta]
+#[tauri::command]
+pub async fn deactivate_license_key(
+    app: AppHandle,
+    license_key: String,
+    device_id: Option<String>,
+    license_manager: State<'_, LicenseManagerState>,
+) -> Result<(), LicenseKeyError> {
+    license_manager
+        .deactivate(license_key.trim(), device_id)
+        .await?;
+    if let Ok(license) = license_manager.refresh_license_status(&app, false).await {
+        license_scheduler::publish(&app, license.to_model());
+    }
+    Ok(())
+}
+
+#[specta::specta]
+#[tauri::command]
+pub async fn license_devices(
+    license_key: String,
+    license_manager: State<'_, LicenseManagerState>,
+) -> Result<Vec<LicenseDevice>, LicenseKeyError> {
+    license_manager.devices(license_key.trim()).await
+}
+
+#[specta::specta]
+#[tauri::command]
+pub async fn redeem_code(
+    app: AppHandle,
+    code: String,
+    license_manager: State<'_, LicenseManagerState>,
+    settings_system: State<'_, SettingsSystemState>,
+) -> Result<model::license::LicenseData, RedeemCodeError> {
+    let code = code.trim().to_uppercase();
+    let license = license_manager.redeem(&code).await?.to_model();
+    {
+        let mut settings_system = settings_system
+            .lock()
+            .expect("settings_system lock required");
+        settings_system.add_redeemed_code(code);
+        settings_system.reset_trial_reminders();
+    }
+    license_scheduler::publish(&app, license.clone());
+    Ok(license)
+}
+
+/// The last known license, without asking the license server.
+#[specta::specta]
+#[tauri::command]
+pub async fn current_license_status(
+    app: AppHandle,
+    license_manager: State<'_, LicenseManagerState>,
+) -> Result<Option<model::license::LicenseData>, String> {
+    Ok(license_manager
+        .get_status(&app, true, false)
+        .await
+        .ok()
+        .map(|data| data.to_model()))
+}
+
+#[specta::specta]
+#[tauri::command]
+pub async fn cancel_payment(
+    license_manager: State<'_, LicenseManagerState>,
+) -> Result<(), ServerError> {
+    license_manager.cancel_payment().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use crate::stand_in_server::{self, StandInResponse};
+    use tauri::http::StatusCode;
+
+    fn license_manager(
+        persistence: Arc<Persistence>,
+        device_id: &model::device::DeviceId,
+    ) -> LicenseManager {
+        LicenseManager {
+            client: HttpClient::for_tests(),
+            device_id: device_id.clone(),
+            status: Arc::new(Mutex::new(None)),
+            persistence,
+            grace_period: Duration::days(7),
+            public_key: Some(license_cache::LOCAL_PUBLIC_KEY),
+        }
+    }
+
+    fn validated_at() -> DateTime<Utc> {
+        "2026-10-01T12:00:00Z".parse().unwrap()
+    }
+
+    fn cache_from_stand_in_server(
+        body: &'static str,
+        signature: &'static str,
+    ) -> (tempfile::TempDir, LicenseManager) {
+        let dir = tempfile::tempdir().unwrap();
+        let persistence = Arc::new(Persistence::with_dir(dir.path().to_path_buf()).unwrap());
+        let manager = license_manager(persistence.clone(), &stand_in_device());
+        let url = stand_in_server::serve(vec![
+            StandInResponse::json(body).header(SIGNATURE_HEADER, signature.trim())
+        ]);
+
+        let (_, cache) = tauri::async_runtime::block_on(async {
+            let response = manager.client.send(manager.client.post(&url)).await?;
+            manager.parse_response(&url, response, validated_at()).await
+        })
+        .unwrap();
+        persistence.save(&cache.expect("signed response")).unwrap();
+
+        (dir, manager)
+    }
+
+    fn stand_in_device() -> model::device::DeviceId {
+        model::device::DeviceId::from_id("stand-in-device")
+    }
+
+    #[test]
+    fn test_paid_license_within_grace_period() {
+        let (_dir, manager) = cache_from_stand_in_server(
+            include_str!("../tests/fixtures/license/active_paid.json"),
+            include_str!("../tests/fixtures/license/active_paid.json.sig"),
+        );
+
+        let data = manager
+            .cached_license_data(validated_at() + Duration::days(3))
+            .unwrap();
+        assert!(matches!(data.status, LicenseStatus::Valid(ValidTypes::Paid(_))));
+        assert_eq!(data.offline_since, Some(validated_at()));
+
+        let data = manager
+            .cached_license_data(validated_at() + Duration::days(8))
+            .unwrap();
+        assert!(matches!(data.status, LicenseStatus::Unknown(_)));
+    }
+
+    #[test]
+    fn test_cached_trial_expires() {
+        let (_dir, manager) = cache_from_stand_in_server(
+            include_str!("../tests/fixtures/license/active_trial.json"),
+            include_str!("../tests/fixtures/license/active_trial.json.sig"),
+        );
+
+        let data = manager
+            .cached_license_data(validated_at() + Duration::days(5))
+            .unwrap();
+        assert!(matches!(data.status, LicenseStatus::Valid(ValidTypes::Trial(_))));
+
+        let data = manager
+            .cached_license_data(validated_at() + Duration::days(7))
+            .unwrap();
+        assert!(matches!(data.status, LicenseStatus::Expired(_)));
+    }
+
+    #[test]
+    fn test_reject_unsigned_or_forged_license() {
+        let dir = tempfile::tempdir().unwrap();
+        let persistence = Arc::new(Persistence::with_dir(dir.path().to_path_buf()).unwrap());
+        let manager = license_manager(persistence, &stand_in_device());
+        let body = include_str!("../tests/fixtures/license/active_paid.json");
+        let license_data = LicenseManager::parse_json(body).unwrap();
+        let verify = |signature: Option<&str>, now: DateTime<Utc>| {
+            manager.verify_response(
+                &license_data,
+                body.to_string(),
+                signature.map(|signature| signature.to_string()),
+                now,
+            )
+        };
+
+        let signature = include_str!("../tests/fixtures/license/active_paid.json.sig");
+        assert!(verify(Some(signature), validated_at()).unwrap().is_some());
+        assert!(matches!(
+            verify(None, validated_at()),
+            Err(ServerError::BadPayload(_))
+        ));
+        // the signature of another response
+        assert!(matches!(
+            verify(
+                Some(include_str!("../tests/fixtures/license/active_trial.json.sig")),
+                validated_at()
+            ),
+            Err(ServerError::BadPayload(_))
+        ));
+        // a replayed response, which is older than the grace period
+        assert!(matches!(
+            verify(Some(signature), validated_at() + Duration::days(8)),
+            Err(ServerError::BadPayload(_))
+        ));
+    }
+
+    #[test]
+    fn test_redeem_code_errors() {
+        let rejected = |error: &str| {
+            RedeemCodeError::from(ServerError::from_status(
+                StatusCode::BAD_REQUEST,
+                &format!(
+                    r#"{{"status":400,"error":"{}","message":"","path":"/app/v1/license/redeem"}}"#,
+                    error
+                ),
+            ))
+        };
+        assert!(matches!(rejected("CODE_EXPIRED"), RedeemCodeError::Expired));
+        assert!(matches!(
+            rejected("CODE_ALREADY_USED"),
+            RedeemCodeError::AlreadyUsed
+        ));
+        assert!(matches!(
+            rejected("CODE_NOT_FOUND"),
+            RedeemCodeError::Invalid
+        ));
+        assert!(matches!(rejected("INVALID_CODE"), RedeemCodeError::Invalid));
+        assert!(matches!(
+            rejected("SOMETHING_ELSE"),
+            RedeemCodeError::Server(ServerError::Rejected { .. })
+        ));
+        assert!(matches!(
+            RedeemCodeError::from(ServerError::from_status(StatusCode::BAD_GATEWAY, "")),
+            RedeemCodeError::Server(ServerError::ServerFailure { status: 502 })
+        ));
+    }
+}
@@ -0,0 +1,162 @@
+use crate::app_config::AppConfig;
+use crate::license_manager::{LicenseData, LicenseStatus, ValidTypes};
+use crate::model::license::LicenseChanged;
+use crate::{model, LicenseManagerState, SettingsManagerState, SettingsSystemState};
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, Wry};
+use tauri_plugin_notification::NotificationExt;
+use tauri_specta::Event;
+
+const STARTUP_DELAY: Duration = Duration::from_secs(60);
+const REFRESH_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+const MIN_RETRY_DELAY: Duration = Duration::from_secs(5 * 60);
+
+/// Refreshes the license periodically, while the app is running. Unreachable
+/// license servers are retried with a backoff.
+pub fn init(app: &AppHandle<Wry>) -> Result<(), anyhow::Error> {
+    let app_handle = app.app_handle().clone();
+    let reminder_days = AppConfig::build().get_trial_reminder_days();
+    tauri::async_runtime::spawn(async move {
+        let mut previous: Option<model::license::LicenseData> = None;
+        let mut failed = false;
+        let mut retry_delay = MIN_RETRY_DELAY;
+        tokio::time::sleep(STARTUP_DELAY).await;
+        loop {
+            if app_handle.state::<SettingsManagerState>().get_settings().is_none() {
+                // the welcome wizard is not finished yet
+                tokio::time::sleep(REFRESH_INTERVAL).await;
+                continue;
+            }
+
+            // a failing refresh is shown once, not every few hours
+            let result = app_handle
+                .state::<LicenseManagerState>()
+                .refresh_license_status(&app_handle, failed)
+                .await;
+            failed = result.is_err();
+
+            let delay = match result {
+                Ok(license_data) => {
+                    let license = license_data.to_model();
+                    if previous.as_ref() != Some(&license) {
+                        publish(&app_handle, license.clone());
+                        previous = Some(license);
+                    }
+                    remind(&app_handle, &license_data, &reminder_days);
+
+                    if license_data.offline_since.is_some() {
+                        next_retry_delay(&mut retry_delay)
+                    } else {
+                        retry_delay = MIN_RETRY_DELAY;
+                        REFRESH_INTERVAL
+                    }
+                }
+                Err(err) if err.is_retryable() => next_retry_delay(&mut retry_delay),
+                Err(_) => REFRESH_INTERVAL,
+            };
+
+            info!("next license refresh in {:?}", delay);
+            tokio::time::sleep(delay).await;
+        }
+    });
+
+    Ok(())
+}
+
+/// Informs all windows about the new license.
+pub fn publish(app: &AppHandle, license: model::license::LicenseData) {
+    LicenseChanged { license }
+        .emit(app)
+        .unwrap_or_else(|err| warn!("unable to emit license change: {:?}", err));
+}
+
+fn next_retry_delay(retry_delay: &mut Duration) -> Duration {
+    let delay = *retry_delay;
+    *retry_delay = (*retry_delay * 2).min(REFRESH_INTERVAL);
+    delay
+}
+
+/// The trial state is stored, so reminders aren't repeated and the expiry is
+/// announced even after a restart.
+fn remind(app: &AppHandle, license_data: &LicenseData, reminder_days: &[u32]) {
+    let settings_system = app.state::<SettingsSystemState>();
+    let mut settings_system = settings_system
+        .lock()
+        .expect("settings_system lock required");
+    match &license_data.status {
+        LicenseStatus::Valid(ValidTypes::Trial(trial)) => {
+            settings_system.set_trial_end(trial.expired_at);
+            let now = Utc::now();
+            let sent_days = settings_system.settings().trial_reminder_sent_days;
+            if let Some(days) = due_trial_reminder(now, trial.expired_at, reminder_days, sent_days) {
+                let days_left = (trial.expired_at - now).num_days();
+                let message = match days_left {
+                    0 => "Your trial of Just Drink! ends today.".to_string(),
+                    1 => "Your trial of Just Drink! ends tomorrow.".to_string(),
+                    days => format!("Your trial of Just Drink! ends in {} days.", days),
+                };
+                notify(app, &message);
+                settings_system.set_trial_reminder_sent(days);
+            }
+        }
+        LicenseStatus::Expired(_) => {
+            let settings = settings_system.settings();
+            if settings.trial_ends_at.is_some() && !settings.trial_expiry_notified {
+                notify(app, "Your trial of Just Drink! has expired.");
+                settings_system.set_trial_expiry_notified();
+            }
+        }
+        _ => {}
+    }
+}
+
+fn notify(app: &AppHandle, message: &str) {
+    info!("show notification: {}", message);
+    app.notification()
+        .builder()
+        .title("Just Drink!")
+        .body(message)
+        .show()
+        .unwrap_or_else(|err| warn!("unable to show notification: {:?}", err));
+}
+
+/// Returns the most urgent reminder threshold, which is reached and has not
+/// been sent yet.
+fn due_trial_reminder(
+    now: DateTime<Utc>,
+    expired_at: DateTime<Utc>,
+    reminder_days: &[u32],
+    sent_days: Option<u32>,
+) -> Option<u32> {
+    if expired_at < now {
+        return None;
+    }
+    let days_left = (expired_at - now).num_days() as u32;
+    reminder_days
+        .iter()
+        .copied()
+        .filter(|days| days_left <= *days)
+        .min()
+        .filter(|days| sent_days.is_none_or(|sent| *days < sent))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_due_trial_reminder() {
+        let now = Utc::now();
+        let days = [3, 1, 0];
+
+        assert_eq!(due_trial_reminder(now, now + Duration::days(5), &days, None), None);
+        assert_eq!(due_trial_reminder(now, now + Duration::hours(60), &days, None), Some(3));
+        assert_eq!(due_trial_reminder(now, now + Duration::hours(60), &days, Some(3)), None);
+        assert_eq!(due_trial_reminder(now, now + Duration::hours(30), &days, Some(3)), Some(1));
+        assert_eq!(due_trial_reminder(now, now + Duration::hours(5), &days, None), Some(0));
+        assert_eq!(due_trial_reminder(now, now - Duration::hours(5), &days, None), None);
+    }
+}
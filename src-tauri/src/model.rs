@@ -1,3 +1,4 @@
+pub mod consent;
 pub mod device;
 pub mod event;
 pub mod license;
@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// The opt-ins for data, which is sent to us without a user action.
+#[derive(Serialize, Deserialize, Debug, Clone, Type, PartialEq)]
+pub struct TelemetryConsent {
+    /// False, until the user answered the consent question of the welcome wizard.
+    pub(crate) answered: bool,
+    pub(crate) usage_analytics: bool,
+    pub(crate) crash_reports: bool,
+    pub(crate) error_reports: bool,
+}
+
+impl TelemetryConsent {
+    pub const UNANSWERED: TelemetryConsent = TelemetryConsent {
+        answered: false,
+        usage_analytics: false,
+        crash_reports: false,
+        error_reports: false,
+    };
+}
@@ -13,6 +13,11 @@ impl DeviceId {
         })
     }
 
+    #[cfg(test)]
+    pub fn from_id(id: &str) -> Self {
+        Self { id: id.to_string() }
+    }
+
     pub fn get_hash_hex_id(&self) -> String {
         let app_store_postfix = if cfg!(feature = "fullversion") {
             "-app-store"
@@ -24,4 +29,13 @@ impl DeviceId {
             Sha256::digest(self.id.as_bytes())
         )
     }
+
+    /// The staged rollout bucket from 0 to 99: the first eight hex digits of the
+    /// hashed id modulo 100, so the server can derive it as well.
+    pub fn rollout_bucket(&self) -> u8 {
+        let hash_hex_id = self.get_hash_hex_id();
+        let prefix = u32::from_str_radix(&hash_hex_id[..8], 16)
+            .expect("the hashed id should start with hex digits");
+        (prefix % 100) as u8
+    }
 }
@@ -1,16 +1,18 @@
+use crate::server_error::ServerError;
 use serde::{Deserialize, Serialize};
 use specta::Type;
 use tauri_specta::Event;
 
-#[derive(Serialize, Deserialize, Debug, Clone, Type, Event)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Type, Event)]
 pub enum LicenseInfoStatus {
     Trial,
     Paid,
     Full,
     Invalid,
+    Unknown,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Type, Event)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Type, Event)]
 pub(crate) enum LicensePaymentStatus {
     GoToCheckout,
     ReadyToCapture,
@@ -19,23 +21,62 @@ pub(crate) enum LicensePaymentStatus {
     Error
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Type, Event)]
+/// A price in the smallest unit of the currency, formatted for the user's locale.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Type)]
+pub struct Price {
+    pub(crate) amount_minor: u32,
+    pub(crate) currency: String,
+    pub(crate) formatted: String,
+    pub(crate) tax_note: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Type, Event)]
 pub(crate) struct LicensePaymentInfo {
     pub(crate) total_trail_days: u32,
     pub(crate) trial_days_left: u32,
-    pub(crate) purchase_price: f64,
+    pub(crate) purchase_price: Price,
+    pub(crate) regular_price: Option<Price>,
     pub(crate) payment_status: LicensePaymentStatus,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Type, Event)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Type, Event)]
 pub struct LicenseInfo {
     pub(crate) status: LicenseInfoStatus,
     pub(crate) license_key: Option<String>,
     pub(crate) message: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Type, Event)]
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+pub struct LicenseDevice {
+    pub(crate) device_id: String,
+    pub(crate) activated_at: String,
+    pub(crate) current: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+pub enum LicenseKeyError {
+    InvalidLicenseKey,
+    SeatLimitReached,
+    DeviceNotActivated,
+    Server(ServerError),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+pub enum RedeemCodeError {
+    Expired,
+    AlreadyUsed,
+    Invalid,
+    Server(ServerError),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Type, Event)]
 pub struct LicenseData {
     pub(crate) payment: LicensePaymentInfo,
     pub(crate) info: LicenseInfo
 }
+
+/// Sent whenever the license changed, e.g. after a background refresh.
+#[derive(Serialize, Deserialize, Debug, Clone, Type, Event)]
+pub struct LicenseChanged {
+    pub(crate) license: LicenseData,
+}
@@ -1,3 +1,4 @@
+use crate::feedback_window::FeedbackRate;
 use crate::model::session::{DrinkCharacter, GenderType, SipSize};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -10,6 +11,8 @@ pub enum WelcomeWizardMode {
     OnlySipSettings,
     OnlyPayment,
     CancelPayment,
+    /// Asks users of older versions for their telemetry consent.
+    OnlyConsent,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Type, Event)]
@@ -24,14 +27,24 @@ pub enum SettingsTabs {
 pub struct Settings {
     pub(crate) app: AppDetails,
     pub(crate) user: SettingsUserDetails,
+    pub(crate) autostart: AutostartStatus,
     pub(crate) selected_tab: SettingsTabs,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Type, Event)]
+pub struct AutostartStatus {
+    pub(crate) enabled: bool,
+    pub(crate) error: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Type, Event)]
 pub struct AppDetails {
     pub(crate) version: String,
     pub(crate) device_id: String,
+    /// Releases are rolled out to the lower buckets first.
+    pub(crate) rollout_bucket: u8,
     pub(crate) url: String,
+    pub(crate) custom_backend: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Type, Event)]
@@ -43,12 +56,28 @@ pub struct SettingsUserDetails {
     pub(crate) gender_type: GenderType,
     pub(crate) consent: bool,
     pub(crate) active: bool,
-    pub(crate) allow_tracking: bool,
     pub(crate) enable_on_startup: bool,
-    pub(crate) beta_version: bool,
+    #[serde(default)]
+    pub(crate) update_channel: UpdateChannel,
+    /// Stay on this version, or move to it once it is released.
+    #[serde(default)]
+    pub(crate) pinned_version: Option<String>,
+    /// Download updates in the background and install them on quit or when
+    /// idle, instead of showing the updater window.
+    #[serde(default)]
+    pub(crate) silent_updates: bool,
     pub(crate) enable_idle_detection: bool,
 }
 
+/// Where updates come from. Older versions only knew `beta_version`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Type, PartialEq, Default)]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    Beta,
+    Nightly,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SettingsSystemDetails {
     #[serde(default = "default_session_count")]
@@ -56,6 +85,74 @@ pub struct SettingsSystemDetails {
     #[serde(default = "default_feedback_provided")]
     pub(crate) feedback_provided: bool,
     pub(crate) last_update_check_date: DateTime<Utc>,
+    #[serde(default)]
+    pub(crate) trial_reminder_sent_days: Option<u32>,
+    /// The end of the last known trial, the reminders belong to it.
+    #[serde(default)]
+    pub(crate) trial_ends_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub(crate) trial_expiry_notified: bool,
+    #[serde(default)]
+    pub(crate) redeemed_codes: Vec<RedeemedCode>,
+    /// Older versions didn't store it, for them it is the first start of this
+    /// version.
+    #[serde(default)]
+    pub(crate) installed_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub(crate) prompt_history: Vec<PromptRecord>,
+    /// Versions, which the user doesn't want to be offered.
+    #[serde(default)]
+    pub(crate) skipped_versions: Vec<String>,
+    #[serde(default)]
+    pub(crate) update_deferral: Option<UpdateDeferral>,
+}
+
+/// The user asked to be reminded of this version later.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UpdateDeferral {
+    pub(crate) version: String,
+    pub(crate) until: DateTime<Utc>,
+}
+
+/// The prompts, which may be shown after a session.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Type, PartialEq)]
+pub enum PromptKind {
+    Feedback,
+    Review,
+    /// The 0-10 "would you recommend" survey.
+    Nps,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Type, PartialEq)]
+pub enum PromptOutcome {
+    Dismissed,
+    Rated,
+    Reviewed,
+    Scored,
+}
+
+/// A shown prompt and the response of the user. Feedback, which the user
+/// gives without a prompt, is recorded as well.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PromptRecord {
+    pub(crate) kind: PromptKind,
+    pub(crate) shown_at: DateTime<Utc>,
+    pub(crate) session_count: u32,
+    /// Missing, while the prompt is shown.
+    #[serde(default)]
+    pub(crate) outcome: Option<PromptOutcome>,
+    #[serde(default)]
+    pub(crate) answered_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub(crate) rating: Option<FeedbackRate>,
+    #[serde(default)]
+    pub(crate) score: Option<u8>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RedeemedCode {
+    pub(crate) code: String,
+    pub(crate) redeemed_at: DateTime<Utc>,
 }
 
 fn default_session_count() -> u32 {
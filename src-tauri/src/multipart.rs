@@ -0,0 +1,102 @@
+use rand::distr::{Alphanumeric, SampleString};
+
+/// A `multipart/form-data` body. The http client is built without the
+/// multipart feature of reqwest, so the body is assembled here.
+pub(crate) struct MultipartForm {
+    boundary: String,
+    body: Vec<u8>,
+}
+
+impl MultipartForm {
+    pub fn new() -> Self {
+        MultipartForm {
+            boundary: format!(
+                "justdrink-{}",
+                Alphanumeric.sample_string(&mut rand::rng(), 24)
+            ),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn json(self, name: &str, value: &serde_json::Value) -> Self {
+        let content = value.to_string().into_bytes();
+        self.part(name, None, "application/json", &content)
+    }
+
+    pub fn file(self, name: &str, file_name: &str, content_type: &str, content: &[u8]) -> Self {
+        self.part(name, Some(file_name), content_type, content)
+    }
+
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    pub fn into_body(mut self) -> Vec<u8> {
+        self.body
+            .extend_from_slice(format!("--{}--\r\n", self.boundary).as_bytes());
+        self.body
+    }
+
+    fn part(
+        mut self,
+        name: &str,
+        file_name: Option<&str>,
+        content_type: &str,
+        content: &[u8],
+    ) -> Self {
+        let disposition = match file_name {
+            Some(file_name) => format!(
+                "form-data; name=\"{}\"; filename=\"{}\"",
+                escape(name),
+                escape(file_name)
+            ),
+            None => format!("form-data; name=\"{}\"", escape(name)),
+        };
+        self.body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: {}\r\nContent-Type: {}\r\n\r\n",
+                self.boundary, disposition, content_type
+            )
+            .as_bytes(),
+        );
+        self.body.extend_from_slice(content);
+        self.body.extend_from_slice(b"\r\n");
+        self
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace(['\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_form_with_json_and_file() {
+        let form = MultipartForm::new()
+            .json("feedback", &json!({"rating": "OK"}))
+            .file("crash_report", "crash-1.json", "application/json", b"{}");
+        let boundary = form.boundary.clone();
+        assert_eq!(
+            form.content_type(),
+            format!("multipart/form-data; boundary={}", boundary)
+        );
+
+        let body = String::from_utf8(form.into_body()).unwrap();
+        assert_eq!(
+            body,
+            format!(
+                "--{b}\r\nContent-Disposition: form-data; name=\"feedback\"\r\nContent-Type: application/json\r\n\r\n{{\"rating\":\"OK\"}}\r\n\
+                 --{b}\r\nContent-Disposition: form-data; name=\"crash_report\"; filename=\"crash-1.json\"\r\nContent-Type: application/json\r\n\r\n{{}}\r\n\
+                 --{b}--\r\n",
+                b = boundary
+            )
+        );
+    }
+}
@@ -0,0 +1,270 @@
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info, warn};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fs;
+use std::fs::File;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, Runtime};
+
+const MAX_BACKUPS: usize = 3;
+const TMP_SUFFIX: &str = ".tmp";
+const CORRUPT_SUFFIX: &str = ".corrupt";
+
+/// App state which is stored as its own json file in the app data directory.
+///
+/// The value is written below `ROOT_KEY`, which keeps the files compatible with
+/// the layout previously written by `tauri_plugin_store`.
+pub(crate) trait Persisted: Serialize + DeserializeOwned {
+    const FILE_NAME: &'static str;
+    const ROOT_KEY: &'static str;
+}
+
+pub(crate) struct Persistence {
+    dir: PathBuf,
+    write_lock: Mutex<()>,
+    /// No store is written anymore, e.g. after the data was deleted.
+    closed: AtomicBool,
+}
+
+impl Persistence {
+    pub fn new<R: Runtime>(app: &AppHandle<R>) -> Result<Self> {
+        Self::with_dir(app.path().app_data_dir()?)
+    }
+
+    pub fn with_dir(dir: PathBuf) -> Result<Self> {
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("unable to create data directory {:?}", dir))?;
+        Ok(Self {
+            dir,
+            write_lock: Mutex::new(()),
+            closed: AtomicBool::new(false),
+        })
+    }
+
+    /// Stops writing the stores until the restart, a running write is finished
+    /// first. Changes are kept in memory only.
+    pub fn close(&self) -> Result<()> {
+        let _guard = self.lock()?;
+        self.closed.store(true, Ordering::SeqCst);
+        info!("stores are closed");
+        Ok(())
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Loads the stored value. A corrupt file is replaced by the newest readable
+    /// backup. Returns `None`, if nothing has been stored yet.
+    pub fn load<T: Persisted>(&self) -> Result<Option<T>> {
+        let path = self.path(T::FILE_NAME, "");
+        let err = match Self::read::<T>(&path) {
+            Ok(value) => return Ok(value),
+            Err(err) => err,
+        };
+
+        warn!("store '{}' is corrupt: {:?}", T::FILE_NAME, err);
+        let _guard = self.lock()?;
+        fs::rename(&path, self.path(T::FILE_NAME, CORRUPT_SUFFIX))
+            .unwrap_or_else(|err| warn!("unable to keep corrupt store: {:?}", err));
+
+        for index in 1..=MAX_BACKUPS {
+            let backup_path = self.backup_path(T::FILE_NAME, index);
+            match Self::read::<T>(&backup_path) {
+                Ok(Some(value)) => {
+                    info!("restore '{}' from backup {}", T::FILE_NAME, index);
+                    Self::write_atomic(&path, &fs::read(&backup_path)?)?;
+                    return Ok(Some(value));
+                }
+                Ok(None) => {}
+                Err(err) => warn!("backup {} of '{}' is corrupt: {:?}", index, T::FILE_NAME, err),
+            }
+        }
+
+        Err(anyhow!(
+            "store '{}' is corrupt and no readable backup exists",
+            T::FILE_NAME
+        ))
+    }
+
+    /// Writes the value atomically. The previous version is kept as a backup, as
+    /// long as it was readable.
+    pub fn save<T: Persisted>(&self, value: &T) -> Result<()> {
+        let mut root = Map::new();
+        root.insert(T::ROOT_KEY.to_string(), serde_json::to_value(value)?);
+        let data = serde_json::to_vec_pretty(&Value::Object(root))?;
+
+        let _guard = self.lock()?;
+        if self.closed.load(Ordering::SeqCst) {
+            debug!("store '{}' is closed, not saved", T::FILE_NAME);
+            return Ok(());
+        }
+        let path = self.path(T::FILE_NAME, "");
+        if let Ok(Some(_)) = Self::read::<T>(&path) {
+            self.rotate_backups(T::FILE_NAME, &path)?;
+        }
+
+        debug!("save store '{}'", T::FILE_NAME);
+        Self::write_atomic(&path, &data)
+    }
+
+    /// Removes the stored value including all backups.
+    pub fn delete<T: Persisted>(&self) -> Result<()> {
+        let _guard = self.lock()?;
+        let mut paths = vec![
+            self.path(T::FILE_NAME, ""),
+            self.path(T::FILE_NAME, CORRUPT_SUFFIX),
+        ];
+        paths.extend((1..=MAX_BACKUPS).map(|index| self.backup_path(T::FILE_NAME, index)));
+
+        for path in paths {
+            match fs::remove_file(&path) {
+                Ok(_) => debug!("removed {:?}", path),
+                Err(err) if err.kind() == ErrorKind::NotFound => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(())
+    }
+
+    fn rotate_backups(&self, file_name: &str, path: &Path) -> Result<()> {
+        for index in (1..MAX_BACKUPS).rev() {
+            let from = self.backup_path(file_name, index);
+            if from.exists() {
+                fs::rename(&from, self.backup_path(file_name, index + 1))?;
+            }
+        }
+        fs::copy(path, self.backup_path(file_name, 1))?;
+        Ok(())
+    }
+
+    fn read<T: Persisted>(path: &Path) -> Result<Option<T>> {
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut root: Map<String, Value> = serde_json::from_slice(&data)?;
+        let value = root
+            .remove(T::ROOT_KEY)
+            .ok_or_else(|| anyhow!("missing root key '{}'", T::ROOT_KEY))?;
+
+        Ok(Some(serde_json::from_value(value)?))
+    }
+
+    fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+        let mut tmp_name = path.as_os_str().to_owned();
+        tmp_name.push(TMP_SUFFIX);
+        let tmp_path = PathBuf::from(tmp_name);
+
+        {
+            let mut file = File::create(&tmp_path)?;
+            file.write_all(data)?;
+            file.sync_all()?;
+        }
+        fs::rename(&tmp_path, path)?;
+
+        #[cfg(unix)]
+        if let Some(parent) = path.parent() {
+            File::open(parent)
+                .and_then(|dir| dir.sync_all())
+                .unwrap_or_else(|err| debug!("unable to sync data directory: {:?}", err));
+        }
+
+        Ok(())
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, ()>> {
+        self.write_lock
+            .lock()
+            .map_err(|e| anyhow!("Failed to lock persistence - mutex poisoned: {:?}", e))
+    }
+
+    fn path(&self, file_name: &str, suffix: &str) -> PathBuf {
+        self.dir.join(format!("{file_name}{suffix}"))
+    }
+
+    fn backup_path(&self, file_name: &str, index: usize) -> PathBuf {
+        self.path(file_name, &format!(".bak{index}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Counter {
+        count: u32,
+    }
+
+    impl Persisted for Counter {
+        const FILE_NAME: &'static str = "counter.json";
+        const ROOT_KEY: &'static str = "data";
+    }
+
+    fn persistence() -> (tempfile::TempDir, Persistence) {
+        let dir = tempfile::tempdir().unwrap();
+        let persistence = Persistence::with_dir(dir.path().to_path_buf()).unwrap();
+        (dir, persistence)
+    }
+
+    #[test]
+    fn test_load_missing_store() {
+        let (_dir, persistence) = persistence();
+        assert_eq!(persistence.load::<Counter>().unwrap(), None);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let (_dir, persistence) = persistence();
+        persistence.save(&Counter { count: 1 }).unwrap();
+        persistence.save(&Counter { count: 2 }).unwrap();
+        assert_eq!(persistence.load::<Counter>().unwrap(), Some(Counter { count: 2 }));
+    }
+
+    #[test]
+    fn test_closed_store_is_not_saved() {
+        let (dir, persistence) = persistence();
+        persistence.close().unwrap();
+        persistence.save(&Counter { count: 1 }).unwrap();
+        assert!(!dir.path().join("counter.json").exists());
+    }
+
+    #[test]
+    fn test_restore_corrupt_store_from_backup() {
+        let (dir, persistence) = persistence();
+        persistence.save(&Counter { count: 1 }).unwrap();
+        persistence.save(&Counter { count: 2 }).unwrap();
+        fs::write(dir.path().join("counter.json"), b"{\"data\": {\"cou").unwrap();
+
+        assert_eq!(persistence.load::<Counter>().unwrap(), Some(Counter { count: 1 }));
+        assert!(dir.path().join("counter.json.corrupt").exists());
+        assert_eq!(persistence.load::<Counter>().unwrap(), Some(Counter { count: 1 }));
+    }
+
+    #[test]
+    fn test_delete_store_with_backups() {
+        let (dir, persistence) = persistence();
+        persistence.save(&Counter { count: 1 }).unwrap();
+        persistence.save(&Counter { count: 2 }).unwrap();
+        persistence.delete::<Counter>().unwrap();
+
+        assert_eq!(persistence.load::<Counter>().unwrap(), None);
+        assert!(!dir.path().join("counter.json.bak1").exists());
+    }
+
+    #[test]
+    fn test_corrupt_store_without_backup() {
+        let (dir, persistence) = persistence();
+        fs::write(dir.path().join("counter.json"), b"not json").unwrap();
+        assert!(persistence.load::<Counter>().is_err());
+    }
+}
@@ -0,0 +1,226 @@
+use crate::model::license::Price;
+use serde::Deserialize;
+
+const DEFAULT_LOCALE: &str = "en-US";
+
+/// A price as sent by the license server.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub(crate) enum ServerPrice {
+    Minor {
+        /// Amount in the smallest unit of the currency, e.g. cents.
+        amount: u32,
+        /// ISO 4217 code, e.g. `EUR`.
+        currency: String,
+        #[serde(default)]
+        tax: Option<ServerTax>,
+    },
+    /// Older license servers and cached licenses contain a plain euro amount.
+    Legacy(f64),
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct ServerTax {
+    name: String,
+    #[serde(rename(deserialize = "ratePercent"))]
+    rate_percent: f64,
+    included: bool,
+}
+
+impl ServerPrice {
+    pub fn to_model(&self, locale: &str) -> Price {
+        match self {
+            ServerPrice::Minor {
+                amount,
+                currency,
+                tax,
+            } => {
+                let currency = currency.to_uppercase();
+                Price {
+                    amount_minor: *amount,
+                    formatted: format(*amount, &currency, locale),
+                    currency,
+                    tax_note: tax.as_ref().map(ServerTax::note),
+                }
+            }
+            ServerPrice::Legacy(amount) => {
+                let amount_minor = (amount * 100.0).round().max(0.0) as u32;
+                Price {
+                    amount_minor,
+                    formatted: format(amount_minor, "EUR", locale),
+                    currency: "EUR".to_string(),
+                    tax_note: None,
+                }
+            }
+        }
+    }
+}
+
+impl ServerTax {
+    fn note(&self) -> String {
+        let rate = format!("{:.2}", self.rate_percent)
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string();
+        if self.included {
+            format!("incl. {}% {}", rate, self.name)
+        } else {
+            format!("plus {}% {}", rate, self.name)
+        }
+    }
+}
+
+/// The locale of the operating system, e.g. `de-DE`.
+pub fn system_locale() -> String {
+    sys_locale::get_locale().unwrap_or_else(|| DEFAULT_LOCALE.to_string())
+}
+
+/// Formats the amount in minor units like the locale does, e.g. `9,99 €` for
+/// `de-DE` and `€9.99` for `en-US`.
+pub fn format(amount_minor: u32, currency: &str, locale: &str) -> String {
+    let (language, region) = split_locale(locale);
+    let style = NumberStyle::of(&language, &region);
+
+    let digits = minor_unit_digits(currency);
+    let divisor = 10u32.pow(digits);
+    let mut number = group(amount_minor / divisor, style.group_separator);
+    if digits > 0 {
+        number.push(style.decimal_separator);
+        number.push_str(&format!(
+            "{:0width$}",
+            amount_minor % divisor,
+            width = digits as usize
+        ));
+    }
+
+    let symbol = currency_symbol(currency);
+    let spaced = symbol.chars().all(|c| c.is_ascii_alphabetic());
+    if style.symbol_first {
+        if spaced || style.space_after_symbol {
+            format!("{} {}", symbol, number)
+        } else {
+            format!("{}{}", symbol, number)
+        }
+    } else {
+        format!("{} {}", number, symbol)
+    }
+}
+
+struct NumberStyle {
+    decimal_separator: char,
+    group_separator: char,
+    symbol_first: bool,
+    space_after_symbol: bool,
+}
+
+impl NumberStyle {
+    fn of(language: &str, region: &str) -> Self {
+        match (language, region) {
+            (_, "CH") | (_, "LI") => NumberStyle {
+                decimal_separator: '.',
+                group_separator: '\'',
+                symbol_first: true,
+                space_after_symbol: true,
+            },
+            ("nl", _) => NumberStyle {
+                decimal_separator: ',',
+                group_separator: '.',
+                symbol_first: true,
+                space_after_symbol: true,
+            },
+            ("de" | "es" | "it" | "pt" | "da" | "id" | "tr" | "el" | "ro" | "hr", _) => {
+                NumberStyle {
+                    decimal_separator: ',',
+                    group_separator: '.',
+                    symbol_first: false,
+                    space_after_symbol: false,
+                }
+            }
+            ("fr" | "nb" | "no" | "sv" | "fi" | "pl" | "cs" | "sk" | "hu" | "ru" | "uk", _) => {
+                NumberStyle {
+                    decimal_separator: ',',
+                    group_separator: ' ',
+                    symbol_first: false,
+                    space_after_symbol: false,
+                }
+            }
+            _ => NumberStyle {
+                decimal_separator: '.',
+                group_separator: ',',
+                symbol_first: true,
+                space_after_symbol: false,
+            },
+        }
+    }
+}
+
+fn split_locale(locale: &str) -> (String, String) {
+    let mut parts = locale.split(['-', '_', '.']);
+    let language = parts.next().unwrap_or_default().to_lowercase();
+    let region = parts
+        .find(|part| part.len() == 2)
+        .unwrap_or_default()
+        .to_uppercase();
+    (language, region)
+}
+
+fn group(value: u32, separator: char) -> String {
+    let digits = value.to_string();
+    let mut grouped = String::new();
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            grouped.push(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+fn minor_unit_digits(currency: &str) -> u32 {
+    match currency {
+        "JPY" | "KRW" | "CLP" | "ISK" | "VND" | "HUF" => 0,
+        "BHD" | "KWD" | "OMR" | "JOD" | "TND" => 3,
+        _ => 2,
+    }
+}
+
+fn currency_symbol(currency: &str) -> &str {
+    match currency {
+        "EUR" => "€",
+        "USD" => "$",
+        "GBP" => "£",
+        "JPY" => "¥",
+        "INR" => "₹",
+        "KRW" => "₩",
+        _ => currency,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_for_locale() {
+        assert_eq!(format(999, "EUR", "de-DE"), "9,99 €");
+        assert_eq!(format(999, "EUR", "en-US"), "€9.99");
+        assert_eq!(format(123456, "USD", "en_US.UTF-8"), "$1,234.56");
+        assert_eq!(format(1500, "JPY", "ja-JP"), "¥1,500");
+        assert_eq!(format(990, "CHF", "de-CH"), "CHF 9.90");
+        assert_eq!(format(12990, "SEK", "sv-SE"), "129,90 SEK");
+    }
+
+    #[test]
+    fn test_parse_server_price() {
+        let price: ServerPrice = serde_json::from_str(
+            r#"{"amount": 1190, "currency": "eur", "tax": {"name": "VAT", "ratePercent": 19.0, "included": true}}"#,
+        )
+        .unwrap();
+        let price = price.to_model("de-DE");
+        assert_eq!(price.formatted, "11,90 €");
+        assert_eq!(price.tax_note.as_deref(), Some("incl. 19% VAT"));
+
+        let legacy: ServerPrice = serde_json::from_str("9.99").unwrap();
+        assert_eq!(legacy.to_model("en-GB").amount_minor, 999);
+    }
+}
@@ -0,0 +1,312 @@
+use crate::app_config::AppConfig;
+use crate::http_client::HttpClient;
+use crate::model::settings::{PromptKind, PromptOutcome, PromptRecord, SettingsSystemDetails};
+use crate::persistence::{Persisted, Persistence};
+use crate::server_error::ServerError;
+use crate::PromptPolicyState;
+use chrono::{DateTime, Duration, Utc};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager};
+
+const STARTUP_DELAY: std::time::Duration = std::time::Duration::from_secs(2 * 60);
+const REFRESH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(24 * 60 * 60);
+
+/// When a prompt may be shown after a session. The rules are checked in order,
+/// the first matching rule decides.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct PromptPolicy {
+    pub rules: Vec<PromptRule>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PromptRule {
+    pub kind: PromptKind,
+    #[serde(default)]
+    pub min_sessions: u32,
+    #[serde(default)]
+    pub min_days_since_install: i64,
+    /// Sessions since the last prompt of any kind.
+    #[serde(default)]
+    pub sessions_between: u32,
+    /// Days since the last prompt of any kind.
+    #[serde(default)]
+    pub cooldown_days: i64,
+    /// Replaces `cooldown_days`, if the last prompt was dismissed.
+    #[serde(default)]
+    pub dismissed_cooldown_days: Option<i64>,
+    /// Only after a prompt with one of these outcomes, any outcome if empty.
+    #[serde(default)]
+    pub after: Vec<PromptOutcome>,
+    /// Never again, once a prompt of this kind had one of these outcomes.
+    #[serde(default)]
+    pub stop_after: Vec<PromptOutcome>,
+    #[serde(default)]
+    pub max_prompts: Option<u32>,
+}
+
+impl Persisted for PromptPolicy {
+    const FILE_NAME: &'static str = "mm-prompt-policy.json";
+    const ROOT_KEY: &'static str = "policy";
+}
+
+impl Default for PromptPolicy {
+    /// Asks happy users for a review, everybody else for feedback until it was
+    /// given, and all of them for the NPS now and then.
+    fn default() -> Self {
+        PromptPolicy {
+            rules: vec![
+                PromptRule {
+                    kind: PromptKind::Review,
+                    min_sessions: 13,
+                    min_days_since_install: 7,
+                    sessions_between: 0,
+                    cooldown_days: 1,
+                    dismissed_cooldown_days: None,
+                    after: vec![PromptOutcome::Rated],
+                    stop_after: vec![PromptOutcome::Reviewed],
+                    max_prompts: Some(2),
+                },
+                PromptRule {
+                    kind: PromptKind::Nps,
+                    min_sessions: 34,
+                    min_days_since_install: 30,
+                    sessions_between: 21,
+                    cooldown_days: 90,
+                    dismissed_cooldown_days: Some(30),
+                    after: vec![],
+                    stop_after: vec![],
+                    max_prompts: None,
+                },
+                PromptRule {
+                    kind: PromptKind::Feedback,
+                    min_sessions: 3,
+                    min_days_since_install: 0,
+                    sessions_between: 10,
+                    cooldown_days: 3,
+                    dismissed_cooldown_days: Some(7),
+                    after: vec![],
+                    stop_after: vec![PromptOutcome::Rated, PromptOutcome::Reviewed],
+                    max_prompts: Some(8),
+                },
+            ],
+        }
+    }
+}
+
+impl PromptPolicy {
+    /// The prompt to show now. Reviews are only asked for, if the store supports
+    /// them.
+    pub fn next_prompt(
+        &self,
+        settings: &SettingsSystemDetails,
+        review_supported: bool,
+        now: DateTime<Utc>,
+    ) -> Option<PromptKind> {
+        self.rules
+            .iter()
+            .find(|rule| {
+                (rule.kind != PromptKind::Review || review_supported) && rule.matches(settings, now)
+            })
+            .map(|rule| rule.kind)
+    }
+}
+
+impl PromptRule {
+    fn matches(&self, settings: &SettingsSystemDetails, now: DateTime<Utc>) -> bool {
+        let history = &settings.prompt_history;
+        let installed_at = settings.installed_at.unwrap_or(now);
+        if settings.session_count < self.min_sessions
+            || now.signed_duration_since(installed_at) < Duration::days(self.min_days_since_install)
+        {
+            return false;
+        }
+
+        let of_kind: Vec<&PromptRecord> = history
+            .iter()
+            .filter(|record| record.kind == self.kind)
+            .collect();
+        if of_kind.iter().any(|record| {
+            record
+                .outcome
+                .is_some_and(|outcome| self.stop_after.contains(&outcome))
+        }) || self
+            .max_prompts
+            .is_some_and(|max_prompts| of_kind.len() >= max_prompts as usize)
+        {
+            return false;
+        }
+
+        let Some(last) = history.last() else {
+            return self.after.is_empty();
+        };
+        let outcome = last.outcome.unwrap_or(PromptOutcome::Dismissed);
+        let cooldown_days = match outcome {
+            PromptOutcome::Dismissed => self.dismissed_cooldown_days.unwrap_or(self.cooldown_days),
+            _ => self.cooldown_days,
+        };
+        (self.after.is_empty() || self.after.contains(&outcome))
+            && settings.session_count.saturating_sub(last.session_count) >= self.sessions_between
+            && now.signed_duration_since(last.shown_at) >= Duration::days(cooldown_days)
+    }
+}
+
+/// Holds the policy. The server may update it, the last received policy is
+/// used while offline.
+pub(crate) struct PromptPolicyManager {
+    client: HttpClient,
+    persistence: Arc<Persistence>,
+    policy: Mutex<PromptPolicy>,
+}
+
+impl PromptPolicyManager {
+    pub fn new(client: HttpClient, persistence: Arc<Persistence>) -> Self {
+        let policy = persistence
+            .load::<PromptPolicy>()
+            .unwrap_or_else(|err| {
+                warn!("cached prompt policy not readable: {:?}", err);
+                None
+            })
+            .unwrap_or_default();
+        PromptPolicyManager {
+            client,
+            persistence,
+            policy: Mutex::new(policy),
+        }
+    }
+
+    pub fn policy(&self) -> PromptPolicy {
+        self.policy
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+    }
+
+    pub async fn refresh(&self) -> Result<(), ServerError> {
+        let url = format!("{}/app/v1/prompt-policy", AppConfig::build().get_url());
+        let policy: PromptPolicy = self
+            .client
+            .send_idempotent(self.client.get(&url))
+            .await?
+            .json()
+            .await
+            .map_err(ServerError::bad_payload)?;
+
+        let mut current = self.policy.lock().unwrap_or_else(|err| err.into_inner());
+        if *current != policy {
+            info!("prompt policy updated to {} rules", policy.rules.len());
+            self.persistence
+                .save(&policy)
+                .unwrap_or_else(|err| error!("unable to cache prompt policy: {:?}", err));
+            *current = policy;
+        }
+        Ok(())
+    }
+}
+
+/// Fetches the policy once a day.
+pub fn init(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(STARTUP_DELAY).await;
+        loop {
+            app.state::<PromptPolicyState>()
+                .refresh()
+                .await
+                .unwrap_or_else(|err| info!("prompt policy not refreshed: {:?}", err));
+            tokio::time::sleep(REFRESH_INTERVAL).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(session_count: u32, history: Vec<PromptRecord>) -> SettingsSystemDetails {
+        SettingsSystemDetails {
+            session_count,
+            feedback_provided: false,
+            last_update_check_date: Utc::now(),
+            trial_reminder_sent_days: None,
+            trial_ends_at: None,
+            trial_expiry_notified: false,
+            redeemed_codes: Vec::new(),
+            installed_at: Some(Utc::now() - Duration::days(60)),
+            prompt_history: history,
+            skipped_versions: Vec::new(),
+            update_deferral: None,
+        }
+    }
+
+    fn record(
+        kind: PromptKind,
+        days_ago: i64,
+        session_count: u32,
+        outcome: PromptOutcome,
+    ) -> PromptRecord {
+        PromptRecord {
+            kind,
+            shown_at: Utc::now() - Duration::days(days_ago),
+            session_count,
+            outcome: Some(outcome),
+            answered_at: None,
+            rating: None,
+            score: None,
+        }
+    }
+
+    #[test]
+    fn test_default_policy() {
+        let policy = PromptPolicy::default();
+        let now = Utc::now();
+
+        assert_eq!(policy.next_prompt(&settings(2, vec![]), true, now), None);
+        assert_eq!(
+            policy.next_prompt(&settings(3, vec![]), true, now),
+            Some(PromptKind::Feedback)
+        );
+
+        // dismissed feedback cools down for a week and ten sessions
+        let dismissed = vec![record(PromptKind::Feedback, 2, 3, PromptOutcome::Dismissed)];
+        assert_eq!(
+            policy.next_prompt(&settings(20, dismissed), true, now),
+            None
+        );
+        let dismissed = vec![record(PromptKind::Feedback, 8, 3, PromptOutcome::Dismissed)];
+        assert_eq!(
+            policy.next_prompt(&settings(13, dismissed), true, now),
+            Some(PromptKind::Feedback)
+        );
+
+        // rated users are asked for a review, if the store supports it
+        let rated = vec![record(PromptKind::Feedback, 2, 10, PromptOutcome::Rated)];
+        assert_eq!(
+            policy.next_prompt(&settings(13, rated.clone()), true, now),
+            Some(PromptKind::Review)
+        );
+        assert_eq!(policy.next_prompt(&settings(13, rated), false, now), None);
+
+        let reviewed = vec![
+            record(PromptKind::Feedback, 100, 10, PromptOutcome::Rated),
+            record(PromptKind::Review, 99, 13, PromptOutcome::Reviewed),
+        ];
+        assert_eq!(
+            policy.next_prompt(&settings(40, reviewed), true, now),
+            Some(PromptKind::Nps)
+        );
+    }
+
+    #[test]
+    fn test_remote_policy_format() {
+        let policy: PromptPolicy = serde_json::from_str(
+            r#"{"rules": [{"kind": "Nps", "minSessions": 5, "cooldownDays": 30, "stopAfter": ["Scored"]}]}"#,
+        )
+        .unwrap();
+        assert_eq!(policy.rules[0].min_sessions, 5);
+        assert_eq!(policy.rules[0].sessions_between, 0);
+        assert_eq!(policy.rules[0].stop_after, vec![PromptOutcome::Scored]);
+    }
+}
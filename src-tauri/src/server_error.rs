@@ -0,0 +1,217 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::error::Error;
+use std::fmt;
+use tauri::http::StatusCode;
+use tauri_plugin_http::reqwest;
+
+/// Error body, which the backend sends for rejected requests.
+#[allow(dead_code)]
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct ErrorResponse {
+    status: u16,
+    pub error: String,
+    pub message: String,
+    path: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+pub enum ServerError {
+    NetworkUnavailable(String),
+    Timeout(String),
+    Rejected {
+        status: u16,
+        error: String,
+        message: String,
+        /// The backend sent its error body, the answer didn't come from a
+        /// proxy or a captive portal.
+        from_backend: bool,
+    },
+    ServerFailure {
+        status: u16,
+    },
+    BadPayload(String),
+    Tls(String),
+}
+
+impl ServerError {
+    /// Retryable errors may go away by themselves, permanent errors need a
+    /// change on the client or the server.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ServerError::NetworkUnavailable(_) => true,
+            ServerError::Timeout(_) => true,
+            ServerError::ServerFailure { .. } => true,
+            ServerError::Rejected { status, .. } => {
+                *status == StatusCode::TOO_MANY_REQUESTS.as_u16()
+            }
+            ServerError::BadPayload(_) => false,
+            ServerError::Tls(_) => false,
+        }
+    }
+
+    /// Whether the backend itself refused the request. Everything else may be
+    /// caused by the network between the app and the backend.
+    pub fn is_authoritative(&self) -> bool {
+        matches!(
+            self,
+            ServerError::Rejected {
+                from_backend: true,
+                ..
+            }
+        ) && !self.is_retryable()
+    }
+
+    /// The error code of the backend for rejected requests.
+    pub fn rejected_code(&self) -> Option<&str> {
+        match self {
+            ServerError::Rejected { error, .. } => Some(error.as_str()),
+            _ => None,
+        }
+    }
+
+    pub fn from_request(err: reqwest::Error) -> Self {
+        let description = Self::describe(&err);
+        if err.is_timeout() {
+            ServerError::Timeout(description)
+        } else if Self::is_tls(&err) {
+            ServerError::Tls(description)
+        } else if err.is_decode() {
+            ServerError::BadPayload(description)
+        } else if let Some(status) = err.status() {
+            Self::from_status(status, "")
+        } else {
+            ServerError::NetworkUnavailable(description)
+        }
+    }
+
+    pub async fn from_response(response: reqwest::Response) -> Self {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        Self::from_status(status, &body)
+    }
+
+    pub fn from_status(status: StatusCode, body: &str) -> Self {
+        if status.is_client_error() {
+            match serde_json::from_str::<ErrorResponse>(body) {
+                Ok(response) => ServerError::Rejected {
+                    status: status.as_u16(),
+                    error: response.error,
+                    message: response.message,
+                    from_backend: true,
+                },
+                Err(_) => ServerError::Rejected {
+                    status: status.as_u16(),
+                    error: status.canonical_reason().unwrap_or("UNKNOWN").to_string(),
+                    message: format!("The server rejected the request ({})", status),
+                    from_backend: false,
+                },
+            }
+        } else if status.is_server_error() {
+            ServerError::ServerFailure {
+                status: status.as_u16(),
+            }
+        } else {
+            ServerError::BadPayload(format!("unexpected response status {}", status))
+        }
+    }
+
+    pub fn bad_payload(err: impl fmt::Debug) -> Self {
+        ServerError::BadPayload(format!("{:?}", err))
+    }
+
+    fn is_tls(err: &reqwest::Error) -> bool {
+        let mut source = err.source();
+        while let Some(cause) = source {
+            if Self::is_tls_cause(cause) {
+                return true;
+            }
+            source = cause.source();
+        }
+        false
+    }
+
+    /// The TLS stack reports handshake and certificate errors as `rustls::Error`,
+    /// the connection wraps them in an `io::Error`.
+    fn is_tls_cause(cause: &(dyn Error + 'static)) -> bool {
+        cause.is::<rustls::Error>()
+            || cause
+                .downcast_ref::<std::io::Error>()
+                .and_then(|err| err.get_ref())
+                .is_some_and(|inner| inner.is::<rustls::Error>())
+    }
+
+    fn describe(err: &reqwest::Error) -> String {
+        let mut description = err.to_string();
+        let mut source = err.source();
+        while let Some(cause) = source {
+            description.push_str(&format!(": {}", cause));
+            source = cause.source();
+        }
+        description
+    }
+}
+
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServerError::NetworkUnavailable(_) => {
+                write!(f, "The server is not reachable. Please check your internet connection.")
+            }
+            ServerError::Timeout(_) => write!(f, "The server did not answer in time."),
+            ServerError::Rejected { message, .. } => write!(f, "{}", message),
+            ServerError::ServerFailure { status } => {
+                write!(f, "The server has a problem ({}). Please try again later.", status)
+            }
+            ServerError::BadPayload(_) => write!(f, "The server sent an unexpected answer."),
+            ServerError::Tls(_) => write!(
+                f,
+                "A secure connection to the server could not be established."
+            ),
+        }
+    }
+}
+
+impl Error for ServerError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejected_with_server_message() {
+        let error = ServerError::from_status(
+            StatusCode::CONFLICT,
+            r#"{"status":409,"error":"SEAT_LIMIT_REACHED","message":"All seats are used","path":"/app/v1/license/activate"}"#,
+        );
+        assert_eq!(error.rejected_code(), Some("SEAT_LIMIT_REACHED"));
+        assert_eq!(error.to_string(), "All seats are used");
+        assert!(!error.is_retryable());
+        assert!(error.is_authoritative());
+    }
+
+    #[test]
+    fn test_rejected_by_proxy() {
+        let error = ServerError::from_status(StatusCode::FORBIDDEN, "<html>blocked</html>");
+        assert!(!error.is_retryable());
+        assert!(!error.is_authoritative());
+    }
+
+    #[test]
+    fn test_tls_cause() {
+        let certificate = std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            rustls::Error::InvalidCertificate(rustls::CertificateError::UnknownIssuer),
+        );
+        assert!(ServerError::is_tls_cause(&certificate));
+        let refused = std::io::Error::new(std::io::ErrorKind::ConnectionRefused, "refused");
+        assert!(!ServerError::is_tls_cause(&refused));
+    }
+
+    #[test]
+    fn test_retryable_status() {
+        assert!(ServerError::from_status(StatusCode::BAD_GATEWAY, "").is_retryable());
+        assert!(ServerError::from_status(StatusCode::TOO_MANY_REQUESTS, "").is_retryable());
+        assert!(!ServerError::from_status(StatusCode::NOT_FOUND, "<html>").is_retryable());
+    }
+}
@@ -1,17 +1,19 @@
 use crate::alert::Alert;
+use crate::error_catalog::ErrorCode;
 use crate::model::settings::WelcomeWizardMode;
 use crate::{
     countdown_timer, feedback_window, tracking, updater_window, welcome_window,
-    CountdownTimerState, LicenseManagerState, SettingsManagerState, SettingsSystemState,
-    TrackingState,
+    CountdownTimerState, LicenseManagerState, PromptPolicyState, SettingsManagerState,
+    SettingsSystemState, TrackingState,
 };
 use anyhow::{anyhow, Error};
+use chrono::Utc;
 use core::clone::Clone;
 use log::info;
 use tauri::{AppHandle, EventId, Manager, State, WebviewWindowBuilder, Wry};
 use tauri_specta::Event;
 
-use crate::feedback_window::FeedbackDisplay;
+use crate::license_manager::LicenseStatus;
 use crate::model::event::SessionStartEvent;
 use crate::model::session::{DrinkCharacter, SipSize};
 
@@ -51,12 +53,7 @@ pub async fn start_session(
     show_session(&app, drink_settings)
         .await
         .unwrap_or_else(|err| {
-            app.alert(
-                "Can't start session",
-                "There was an error while trying to start the session.",
-                Some(err),
-                false,
-            );
+            app.alert(ErrorCode::SessionStartFailed, Some(err), false);
         });
     Ok(())
 }
@@ -65,19 +62,27 @@ pub async fn show_session(
     app: &AppHandle<Wry>,
     overwrite_settings: Option<SessionStartEvent>,
 ) -> Result<(), anyhow::Error> {
-    let license_active = app
+    let license_status = app
         .state::<LicenseManagerState>()
         .get_status(&app.app_handle(), false, false)
         .await
-        .map(|s| s.status.is_active())
+        .map(|s| s.status);
+
+    if let Ok(LicenseStatus::Unknown(reason)) = &license_status {
+        app.alert_with_detail(
+            ErrorCode::LicenseUnverified,
+            Some(reason.to_string()),
+            None,
+            false,
+        );
+        return Ok(());
+    }
+
+    let license_active = license_status
+        .map(|status| status.is_active())
         .map_err(|err| anyhow!(err))
         .unwrap_or_else(|err| {
-            app.alert(
-                "Unable to access license server",
-                "We are sorry, but we have trouble accessing the license server.",
-                Some(err),
-                true,
-            );
+            app.alert(ErrorCode::LicenseServerUnreachable, Some(err), true);
             false
         });
 
@@ -94,7 +99,7 @@ pub async fn show_session(
                 let mut settings_system = settings_system
                     .lock()
                     .map_err(|e| anyhow::anyhow!(e.to_string()))?;
-                settings_system.increase_session_count(&app);
+                settings_system.increase_session_count();
             }
 
             // send tracking event
@@ -133,12 +138,7 @@ pub async fn show_session(
             info!("start session window: send event");
             session_start.emit(app.app_handle())?;
         } else {
-            app.alert(
-                "Session Window Missing",
-                "I am sorry, this should not happen. Please contact Rocket Solutions",
-                None,
-                false,
-            );
+            app.alert(ErrorCode::SessionWindowMissing, None, false);
         }
     } else {
         welcome_window::show(
@@ -188,23 +188,28 @@ pub(crate) fn days_between(
 pub async fn end_session(
     app: AppHandle,
     settings_system: State<'_, SettingsSystemState>,
+    prompt_policy: State<'_, PromptPolicyState>,
     demo_mode: bool,
 ) -> Result<(), String> {
     info!("end reminder session");
     hide_window(&app)?;
 
     if !demo_mode {
-        let ask_for_feedback = {
+        let prompt = {
             let ss = settings_system
                 .lock()
                 .expect("settings_system should not be locked");
-            ss.should_show_feedback()
+            prompt_policy.policy().next_prompt(
+                &ss.settings(),
+                feedback_window::is_full_version_and_mac(),
+                Utc::now(),
+            )
         };
 
         let updater_visible = updater_window::show_if_update_available(&app, false, false).await;
 
-        if ask_for_feedback && !updater_visible {
-            feedback_window::show(&app).expect("unable to show feedback window");
+        if let Some(kind) = prompt.filter(|_| !updater_visible) {
+            feedback_window::show_prompt(&app, kind).expect("unable to show feedback window");
         }
     }
 
@@ -219,3 +224,10 @@ pub fn hide_window(app: &AppHandle) -> Result<(), String> {
     }
     Ok(())
 }
+
+/// Whether a reminder session is showing right now.
+pub fn is_visible(app: &AppHandle) -> bool {
+    app.get_webview_window(WINDOW_LABEL)
+        .and_then(|window| window.is_visible().ok())
+        .unwrap_or(false)
+}
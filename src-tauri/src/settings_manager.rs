@@ -1,13 +1,12 @@
 use crate::model::session::{DrinkCharacter, GenderType, SipSize};
-use crate::model::settings::SettingsUserDetails;
+use crate::consent;
+use crate::model::settings::{SettingsUserDetails, UpdateChannel};
+use crate::persistence::{Persisted, Persistence};
 use anyhow::Result;
 use log::{info, warn};
 use serde::{Deserialize, Serialize};
 use std::sync::{Arc, Mutex};
-use tauri::{AppHandle, Manager}; // For Tauri state management
-use tauri_plugin_store::{Store, StoreBuilder};
-
-const STORE_NAME: &str = "mm-config.json";
+use tauri::AppHandle;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct UserSettingsStore {
@@ -15,6 +14,11 @@ pub struct UserSettingsStore {
     pub user: SettingsUserDetails,
 }
 
+impl Persisted for UserSettingsStore {
+    const FILE_NAME: &'static str = "mm-config.json";
+    const ROOT_KEY: &'static str = "data";
+}
+
 impl Default for UserSettingsStore {
     fn default() -> Self {
         UserSettingsStore {
@@ -27,25 +31,90 @@ impl Default for UserSettingsStore {
                 next_break_duration_minutes: 15,
                 drink_amount_ml: 3000,
                 active: true,
-                beta_version: false,
+                update_channel: UpdateChannel::Stable,
+                pinned_version: None,
+                silent_updates: false,
                 enable_on_startup: true,
                 enable_idle_detection: true,
-                allow_tracking: true,
             },
         }
     }
 }
 
+/// The settings of older versions, which stored `beta_version` instead of the
+/// channel and `allow_tracking` instead of the telemetry consent.
+#[derive(Serialize, Deserialize, Debug)]
+struct LegacySettings {
+    user: LegacyUserSettings,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct LegacyUserSettings {
+    #[serde(default)]
+    beta_version: bool,
+    #[serde(default)]
+    update_channel: Option<UpdateChannel>,
+    #[serde(default)]
+    allow_tracking: Option<bool>,
+}
+
+impl Persisted for LegacySettings {
+    const FILE_NAME: &'static str = UserSettingsStore::FILE_NAME;
+    const ROOT_KEY: &'static str = UserSettingsStore::ROOT_KEY;
+}
+
+/// The settings as written. A pinned version may be older and requires the
+/// fields of older versions.
+#[derive(Serialize, Deserialize, Debug)]
+struct StoredSettings {
+    version: String,
+    user: StoredUserSettings,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct StoredUserSettings {
+    #[serde(flatten)]
+    user: SettingsUserDetails,
+    beta_version: bool,
+    allow_tracking: bool,
+}
+
+impl Persisted for StoredSettings {
+    const FILE_NAME: &'static str = UserSettingsStore::FILE_NAME;
+    const ROOT_KEY: &'static str = UserSettingsStore::ROOT_KEY;
+}
+
+impl StoredSettings {
+    fn new(settings: UserSettingsStore) -> Self {
+        StoredSettings {
+            version: settings.version,
+            user: StoredUserSettings {
+                beta_version: settings.user.update_channel == UpdateChannel::Beta,
+                allow_tracking: consent::current().usage_analytics,
+                user: settings.user,
+            },
+        }
+    }
+}
+
+/// The tracking opt-in of older versions, if the settings were written by one.
+pub(crate) fn legacy_allow_tracking(persistence: &Persistence) -> Option<bool> {
+    persistence
+        .load::<LegacySettings>()
+        .ok()
+        .flatten()
+        .and_then(|legacy| legacy.user.allow_tracking)
+}
+
 pub struct SettingsManager {
-    store: Arc<Store<tauri::Wry>>,
+    persistence: Arc<Persistence>,
     version: String,
     settings: Mutex<Option<UserSettingsStore>>,
 }
 
 impl SettingsManager {
     // Initialize the SettingsManager
-    pub fn new(app: &AppHandle) -> Result<Self> {
-        let store = StoreBuilder::new(app.app_handle(), STORE_NAME).build()?;
+    pub fn new(app: &AppHandle, persistence: Arc<Persistence>) -> Result<Self> {
         let settings = Mutex::new(None);
         let version = app
             .config()
@@ -53,7 +122,7 @@ impl SettingsManager {
             .clone()
             .unwrap_or_else(|| "0.0.0".to_string());
         let sm = Self {
-            store,
+            persistence,
             version,
             settings,
         };
@@ -64,18 +133,24 @@ impl SettingsManager {
 
     // Load settings from the store
     pub fn load(&self) -> Result<()> {
-        let data_json = self
-            .store
-            .get("data".to_string())
-            .ok_or_else(|| anyhow::anyhow!("Can't find settings in data"))?;
-
-        let user_settings: UserSettingsStore = serde_json::from_value(data_json.clone())?;
+        let mut user_settings = self
+            .persistence
+            .load::<UserSettingsStore>()?
+            .ok_or_else(|| {
+                anyhow::anyhow!("Can't find settings in {}", UserSettingsStore::FILE_NAME)
+            })?;
+        if let Ok(Some(legacy)) = self.persistence.load::<LegacySettings>() {
+            if legacy.user.update_channel.is_none() && legacy.user.beta_version {
+                info!("migrate beta access to the beta update channel");
+                user_settings.user.update_channel = UpdateChannel::Beta;
+            }
+        }
 
         {
             let mut settings_guard = self.settings.lock().map_err(|e| {
                 anyhow::anyhow!("Failed to lock settings - mutex poisoned: {:?}", e)
             })?;
-            *settings_guard = Some(user_settings.clone());
+            *settings_guard = Some(user_settings);
         }
         Ok(())
     }
@@ -114,12 +189,7 @@ impl SettingsManager {
         };
 
         if let Some(s) = settings {
-            // Serialize the settings
-            let json_data = serde_json::to_value(s)?;
-
-            // Update the store
-            self.store.set("data".to_string(), json_data);
-            self.store.save()?;
+            self.persistence.save(&StoredSettings::new(s))?;
         }
 
         Ok(())
@@ -134,3 +204,24 @@ impl SettingsManager {
         settings.clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_fields_of_older_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let persistence = Persistence::with_dir(dir.path().to_path_buf()).unwrap();
+        let mut settings = UserSettingsStore::default();
+        settings.user.update_channel = UpdateChannel::Beta;
+
+        persistence.save(&StoredSettings::new(settings)).unwrap();
+
+        let legacy = persistence.load::<LegacySettings>().unwrap().unwrap();
+        assert!(legacy.user.beta_version);
+        assert!(legacy.user.allow_tracking.is_some());
+        let stored = persistence.load::<UserSettingsStore>().unwrap().unwrap();
+        assert_eq!(stored.user.update_channel, UpdateChannel::Beta);
+    }
+}
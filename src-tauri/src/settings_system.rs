@@ -1,93 +1,230 @@
-use crate::model::settings::SettingsSystemDetails;
+use crate::feedback_window::FeedbackRate;
+use crate::model::settings::{
+    PromptKind, PromptOutcome, PromptRecord, RedeemedCode, SettingsSystemDetails, UpdateDeferral,
+};
+use crate::persistence::{Persisted, Persistence};
 use anyhow::Error;
-use chrono::{Duration, Utc};
-use log::{debug, error, warn};
-use std::string::ToString;
-use tauri::{AppHandle, Manager, Runtime};
-use tauri_plugin_store::StoreBuilder;
+use chrono::{DateTime, Duration, Utc};
+use log::{error, warn};
+use std::sync::Arc;
 
-const STORE_NAME: &str = "mm-system-config.json";
-const ROOT_PATH: &str = "system";
+impl Persisted for SettingsSystemDetails {
+    const FILE_NAME: &'static str = "mm-system-config.json";
+    const ROOT_KEY: &'static str = "system";
+}
 
 #[derive(Clone)]
 pub(crate) struct SettingsSystem {
+    persistence: Arc<Persistence>,
     settings: SettingsSystemDetails,
 }
 
 impl SettingsSystem {
-    pub fn load(app: &AppHandle) -> SettingsSystem {
-        let settings = Self::load_settings_store(app).unwrap_or_else(|err| {
-            warn!("system store settings not found: {:?}", err);
-            SettingsSystemDetails {
-                session_count: 0,
-                feedback_provided: false,
-                last_update_check_date: Utc::now(),
-            }
-        });
-        SettingsSystem { settings }
+    pub fn load(persistence: Arc<Persistence>) -> SettingsSystem {
+        let mut settings = persistence
+            .load::<SettingsSystemDetails>()
+            .and_then(|settings| {
+                settings.ok_or_else(|| anyhow::anyhow!("no system settings stored yet"))
+            })
+            .unwrap_or_else(|err| {
+                warn!("system store settings not found: {:?}", err);
+                SettingsSystemDetails {
+                    session_count: 0,
+                    feedback_provided: false,
+                    last_update_check_date: Utc::now(),
+                    trial_reminder_sent_days: None,
+                    trial_ends_at: None,
+                    trial_expiry_notified: false,
+                    redeemed_codes: Vec::new(),
+                    installed_at: None,
+                    prompt_history: Vec::new(),
+                    skipped_versions: Vec::new(),
+                    update_deferral: None,
+                }
+            });
+        let migrated = migrate_prompt_history(&mut settings);
+        let settings_system = SettingsSystem {
+            persistence,
+            settings,
+        };
+        if migrated {
+            settings_system
+                .write_settings()
+                .unwrap_or_else(|err| error!("unable to write system settings {}", err));
+        }
+        settings_system
     }
 
     pub fn settings(&self) -> SettingsSystemDetails {
         self.settings.clone()
     }
 
-    pub fn feedback_given<R>(&mut self, app: &AppHandle<R>) -> ()
-    where
-        R: Runtime,
-    {
-        self.settings.feedback_provided = true;
-        self.write_settings(app)
+    pub fn prompt_shown(&mut self, kind: PromptKind) {
+        self.settings.prompt_history.push(PromptRecord {
+            kind,
+            shown_at: Utc::now(),
+            session_count: self.settings.session_count,
+            outcome: None,
+            answered_at: None,
+            rating: None,
+            score: None,
+        });
+        self.write_settings()
             .unwrap_or_else(|err| error!("unable to write system settings {}", err))
     }
 
-    pub fn increase_session_count<R>(&mut self, app: &AppHandle<R>) -> ()
-    where
-        R: Runtime,
-    {
+    /// The prompt, which is shown right now.
+    pub fn pending_prompt(&self) -> Option<PromptKind> {
+        self.settings
+            .prompt_history
+            .last()
+            .filter(|record| record.outcome.is_none())
+            .map(|record| record.kind)
+    }
+
+    /// Stores the response to the shown prompt. Responses without a prompt, e.g.
+    /// feedback from the tray menu, are recorded as well.
+    pub fn prompt_answered(
+        &mut self,
+        kind: PromptKind,
+        outcome: PromptOutcome,
+        rating: Option<FeedbackRate>,
+        score: Option<u8>,
+    ) {
+        let now = Utc::now();
+        if self.pending_prompt() != Some(kind) {
+            self.prompt_shown(kind);
+        }
+        if let Some(record) = self.settings.prompt_history.last_mut() {
+            record.outcome = Some(outcome);
+            record.answered_at = Some(now);
+            record.rating = rating;
+            record.score = score;
+        }
+        if matches!(outcome, PromptOutcome::Rated | PromptOutcome::Reviewed) {
+            self.settings.feedback_provided = true;
+        }
+        self.write_settings()
+            .unwrap_or_else(|err| error!("unable to write system settings {}", err))
+    }
+
+    pub fn prompt_dismissed(&mut self) {
+        if let Some(kind) = self.pending_prompt() {
+            self.prompt_answered(kind, PromptOutcome::Dismissed, None, None);
+        }
+    }
+
+    pub fn increase_session_count(&mut self) {
         self.settings.session_count += 1;
-        self.write_settings(app)
+        self.write_settings()
             .unwrap_or_else(|err| error!("unable to write system settings {}", err))
     }
 
-    pub fn set_last_check_date<R>(&mut self, app: &AppHandle<R>) -> Result<(), Error>
-    where
-        R: Runtime,
-    {
+    pub fn set_last_check_date(&mut self) -> Result<(), Error> {
         self.settings.last_update_check_date = Utc::now();
-        self.write_settings(app)?;
+        self.write_settings()?;
         Ok(())
     }
 
-    pub fn updater_check_needed(&self) -> bool {
-        (self.settings.last_update_check_date + Duration::days(2)) < Utc::now()
+    /// Remembers the end of the current trial. A new or extended trial gets all
+    /// reminders again.
+    pub fn set_trial_end(&mut self, ends_at: DateTime<Utc>) {
+        if self.settings.trial_ends_at == Some(ends_at) {
+            return;
+        }
+        self.settings.trial_ends_at = Some(ends_at);
+        self.settings.trial_reminder_sent_days = None;
+        self.settings.trial_expiry_notified = false;
+        self.write_settings()
+            .unwrap_or_else(|err| error!("unable to write system settings {}", err))
     }
 
-    fn write_settings<R>(&self, app: &AppHandle<R>) -> Result<(), anyhow::Error>
-    where
-        R: Runtime,
-    {
-        debug!("save system settings");
-        let store = StoreBuilder::new(app.app_handle(), STORE_NAME).build()?;
+    pub fn set_trial_expiry_notified(&mut self) {
+        self.settings.trial_expiry_notified = true;
+        self.write_settings()
+            .unwrap_or_else(|err| error!("unable to write system settings {}", err))
+    }
+
+    /// The trial may have been extended, e.g. by a redeemed code.
+    pub fn reset_trial_reminders(&mut self) {
+        self.settings.trial_reminder_sent_days = None;
+        self.settings.trial_expiry_notified = false;
+        self.write_settings()
+            .unwrap_or_else(|err| error!("unable to write system settings {}", err))
+    }
 
-        let json_data = serde_json::to_value(self.settings.clone())
-            .map_err(|e| tauri_plugin_store::Error::Serialize(Box::new(e)))?;
+    pub fn set_trial_reminder_sent(&mut self, days: u32) {
+        self.settings.trial_reminder_sent_days = Some(days);
+        self.write_settings()
+            .unwrap_or_else(|err| error!("unable to write system settings {}", err))
+    }
 
-        store.set(ROOT_PATH.to_string(), json_data);
-        store.save()?;
+    pub fn add_redeemed_code(&mut self, code: String) {
+        self.settings.redeemed_codes.push(RedeemedCode {
+            code,
+            redeemed_at: Utc::now(),
+        });
+        self.write_settings()
+            .unwrap_or_else(|err| error!("unable to write system settings {}", err))
+    }
 
-        Ok(())
+    pub fn skip_version(&mut self, version: String) {
+        if !self.settings.skipped_versions.contains(&version) {
+            self.settings.skipped_versions.push(version);
+        }
+        self.write_settings()
+            .unwrap_or_else(|err| error!("unable to write system settings {}", err))
     }
 
-    fn load_settings_store(app: &AppHandle) -> Result<SettingsSystemDetails, anyhow::Error> {
-        let store = StoreBuilder::new(app.app_handle(), STORE_NAME).build()?;
+    pub fn defer_update(&mut self, version: String, until: DateTime<Utc>) {
+        self.settings.update_deferral = Some(UpdateDeferral { version, until });
+        self.write_settings()
+            .unwrap_or_else(|err| error!("unable to write system settings {}", err))
+    }
 
-        let data_json = store
-            .get(ROOT_PATH.to_string())
-            .ok_or_else(|| anyhow::anyhow!("can't find settings in {}", ROOT_PATH))?;
+    pub fn is_update_deferred(&self, version: &str) -> bool {
+        self.settings
+            .update_deferral
+            .as_ref()
+            .is_some_and(|deferral| deferral.version == version && Utc::now() < deferral.until)
+    }
 
-        let settings: SettingsSystemDetails = serde_json::from_value(data_json.clone())
-            .map_err(|e| tauri_plugin_store::Error::Deserialize(Box::new(e)))?;
+    pub fn updater_check_needed(&self) -> bool {
+        (self.settings.last_update_check_date + Duration::days(2)) < Utc::now()
+    }
+
+    fn write_settings(&self) -> Result<(), Error> {
+        self.persistence.save(&self.settings)
+    }
+}
 
-        Ok(settings)
+/// Prompts, which were still shown when the app quit, count as dismissed. The
+/// feedback of older versions is recorded, so it isn't asked for again.
+fn migrate_prompt_history(settings: &mut SettingsSystemDetails) -> bool {
+    let mut migrated = false;
+    if settings.installed_at.is_none() {
+        settings.installed_at = Some(Utc::now());
+        migrated = true;
+    }
+    if settings.feedback_provided && settings.prompt_history.is_empty() {
+        settings.prompt_history.push(PromptRecord {
+            kind: PromptKind::Feedback,
+            shown_at: settings.last_update_check_date,
+            session_count: settings.session_count,
+            outcome: Some(PromptOutcome::Rated),
+            answered_at: Some(settings.last_update_check_date),
+            rating: None,
+            score: None,
+        });
+        migrated = true;
+    }
+    for record in settings
+        .prompt_history
+        .iter_mut()
+        .filter(|record| record.outcome.is_none())
+    {
+        record.outcome = Some(PromptOutcome::Dismissed);
+        migrated = true;
     }
+    migrated
 }
@@ -1,8 +1,9 @@
 use crate::alert::Alert;
 use crate::app_config::AppConfig;
+use crate::error_catalog::ErrorCode;
 use crate::model::settings::SettingsTabs;
 use crate::settings_manager::UserSettingsStore;
-use crate::{model, CountdownTimerState, SettingsManagerState, TrackingState};
+use crate::{model, AutostartState, CountdownTimerState, SettingsManagerState, TrackingState};
 use log::info;
 use std::string::ToString;
 use std::time::Duration;
@@ -15,12 +16,7 @@ pub(crate) const WINDOW_LABEL: &'static str = "settings";
 pub async fn open_settings(app_handle: AppHandle) -> () {
     info!("open settings window");
     show(app_handle.app_handle(), SettingsTabs::Session).unwrap_or_else(|err| {
-        app_handle.alert(
-            "Can't open settings",
-            "Error while opening settings. Please try again later.",
-            Some(err),
-            false,
-        )
+        app_handle.alert(ErrorCode::SettingsUnavailable, Some(err), false)
     });
 }
 
@@ -30,6 +26,7 @@ pub async fn load_settings(
     app: AppHandle,
     settings: State<'_, SettingsManagerState>,
     tracking: State<'_, TrackingState>,
+    autostart: State<'_, AutostartState>,
 ) -> Result<model::settings::Settings, String> {
     info!("load settings data");
     let version = app.app_handle().config().version.clone();
@@ -42,10 +39,13 @@ pub async fn load_settings(
     Ok(model::settings::Settings {
         app: model::settings::AppDetails {
             device_id: tracking.device_id().get_hash_hex_id(),
+            rollout_bucket: tracking.device_id().rollout_bucket(),
             version: version.unwrap_or("unknown".to_string()),
             url: AppConfig::build().get_url(),
+            custom_backend: AppConfig::build().is_custom_backend(),
         },
         user: settings.user,
+        autostart: autostart.status(),
         selected_tab: SettingsTabs::Session,
     })
 }
@@ -63,7 +63,8 @@ pub fn update_settings(
     settings: model::settings::SettingsUserDetails,
     settings_manager: State<SettingsManagerState>,
     timer: State<CountdownTimerState>,
-) -> () {
+    autostart: State<AutostartState>,
+) -> model::settings::AutostartStatus {
     if settings.active {
         timer.start(Duration::from_secs(
             (settings.next_break_duration_minutes * 60).into(),
@@ -72,17 +73,15 @@ pub fn update_settings(
         timer.stop();
     }
 
+    let enable_on_startup = settings.enable_on_startup;
     settings_manager
         .update_user(settings)
         .unwrap_or_else(|err| {
-            app_handle.alert(
-                "Failed to update settings",
-                "Just Drink! is unable to update settings.",
-                Some(err),
-                false,
-            );
+            app_handle.alert(ErrorCode::SettingsNotSaved, Some(err), false);
             ()
         });
+
+    autostart.reconcile(&app_handle, enable_on_startup)
 }
 
 #[specta::specta]
@@ -91,13 +90,9 @@ pub fn open_browser(window: Window, app_handle: AppHandle, url: String, close: b
     match webbrowser::open(url.as_str()) {
         Ok(_) => {}
         Err(err) => {
-            let error = format!(
-                "I am sorry, we are not able to open up the browser for '{}'",
-                url
-            );
-            app_handle.alert(
-                "Could not open Browser",
-                error.as_str(),
+            app_handle.alert_with_detail(
+                ErrorCode::BrowserUnavailable,
+                Some(url.clone()),
                 Some(anyhow::anyhow!(err)),
                 false,
             );
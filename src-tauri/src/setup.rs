@@ -1,13 +1,15 @@
 use std::error::Error;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use log::{info, warn};
 use tauri::{App, Manager};
-use tauri_plugin_aptabase::EventTracker;
 use tauri_specta::Builder;
-use crate::{dashboard_window, detect_idling, feedback_window, license_manager, model, session_window, settings_system, show_dashboard, subscription_manager, tray, updater_window, welcome_window, CountdownTimerState, FeedbackSenderState, LicenseManagerState, SettingsManagerState, SettingsSystemState, SubscriptionManagerState, TrackingState};
+use crate::{audit_log, autostart, checkout_watcher, consent, crash_reporter, data_eraser, dashboard_window, detect_idling, diagnostics, feedback_window, license_manager, license_scheduler, model, prompt_policy, session_window, settings_system, show_dashboard, subscription_manager, tracking, tray, update_manager, updater_window, welcome_window, CountdownTimerState, FeedbackSenderState, LicenseManagerState, PromptPolicyState, SettingsManagerState, AutostartState, CheckoutWatcherState, DataEraserState, PersistenceState, SettingsSystemState, SubscriptionManagerState, TrackingState, UpdateManagerState};
+use crate::app_config::AppConfig;
 use crate::countdown_timer::CountdownTimer;
+use crate::http_client::HttpClient;
 use crate::model::settings::WelcomeWizardMode;
+use crate::persistence::Persistence;
 use crate::settings_manager::SettingsManager;
 use crate::tracking::Tracking;
 
@@ -16,35 +18,89 @@ use tauri::ActivationPolicy;
 
 
 pub fn setup(builder: Builder, app: &mut App) -> Result<(), Box<dyn Error>> {
-    app.track_event("app_started", None);
     builder.mount_events(app.app_handle());
+    AppConfig::init(app.path().app_config_dir().ok());
+    audit_log::init(app.path().app_log_dir()?);
+    crash_reporter::init(app.app_handle())?;
     let device_id = model::device::DeviceId::lookup()?;
     info!(
                 "application start, device id: {}",
                 &device_id.get_hash_hex_id()
             );
 
-    app.manage::<LicenseManagerState>(license_manager::LicenseManager::new(&device_id));
-    app.manage::<FeedbackSenderState>(feedback_window::FeedbackSender::new(&device_id));
+    let persistence = Arc::new(Persistence::new(app.app_handle())?);
+    app.manage::<PersistenceState>(persistence.clone());
+    consent::init(&persistence);
+
+    let app_config = AppConfig::build();
+    let http_client = HttpClient::new(app.app_handle(), &app_config)?;
+
+    app.manage::<LicenseManagerState>(license_manager::LicenseManager::new(
+        &device_id,
+        http_client.clone(),
+        persistence.clone(),
+        &app_config,
+    ));
+    app.manage::<FeedbackSenderState>(feedback_window::FeedbackSender::new(
+        &device_id,
+        http_client.clone(),
+        persistence.clone(),
+    ));
+    app.manage::<PromptPolicyState>(prompt_policy::PromptPolicyManager::new(
+        http_client.clone(),
+        persistence.clone(),
+    ));
+    app.manage::<UpdateManagerState>(update_manager::UpdateManager::new(
+        persistence.clone(),
+        &device_id,
+    ));
     app.manage::<SubscriptionManagerState>(subscription_manager::SubscriptionManager::new(
         device_id.clone(),
+        http_client.clone(),
+    ));
+    app.manage::<DataEraserState>(data_eraser::DataEraser::new(
+        &device_id,
+        http_client.clone(),
     ));
 
-    let settings_manager = SettingsManager::new(app.app_handle())?;
+    let settings_manager = SettingsManager::new(app.app_handle(), persistence.clone())?;
 
+    app.manage::<AutostartState>(autostart::Autostart::new());
+    app.manage::<CheckoutWatcherState>(checkout_watcher::CheckoutWatcher::new());
     app.manage::<CountdownTimerState>(CountdownTimer::new(app.app_handle()));
     app.manage::<SettingsManagerState>(settings_manager);
-    app.manage::<TrackingState>(Tracking::new(&device_id, app.app_handle())?);
-    app.manage::<SettingsSystemState>(Mutex::new(settings_system::SettingsSystem::load(
+    app.manage::<TrackingState>(Tracking::new(
+        &device_id,
         app.app_handle(),
+        http_client,
+        persistence.clone(),
+        &app_config,
+    )?);
+    app.manage::<SettingsSystemState>(Mutex::new(settings_system::SettingsSystem::load(
+        persistence,
     )));
 
+    tracking::init(app.handle());
+    tracking::track(app.handle(), tracking::Event::AppStarted);
+
     tray::create_tray(app.handle())?;
 
     match app.state::<SettingsManagerState>().get_settings() {
         Some(settings) => {
+            app.state::<AutostartState>()
+                .reconcile(app.app_handle(), settings.user.enable_on_startup);
             tray::show_tray_icon(app.app_handle());
-            if dashboard_window::should_show_dashboard() {
+            if !consent::current().answered {
+                info!("telemetry consent is missing, ask for it");
+                let app = app.app_handle().clone();
+                tauri::async_runtime::block_on(async move {
+                    welcome_window::show(app.app_handle(), &device_id, WelcomeWizardMode::OnlyConsent)
+                        .await
+                })?;
+            } else if let Some(report) = crash_reporter::latest() {
+                info!("crash report {} found, offer to send it", report.id);
+                feedback_window::show(app.app_handle())?;
+            } else if dashboard_window::should_show_dashboard() {
                 show_dashboard(app.app_handle());
             }
             app.state::<CountdownTimerState>()
@@ -67,6 +123,10 @@ pub fn setup(builder: Builder, app: &mut App) -> Result<(), Box<dyn Error>> {
 
     session_window::init(app.app_handle())?;
     detect_idling::init(app.app_handle())?;
+    license_scheduler::init(app.app_handle())?;
+    feedback_window::init(app.app_handle());
+    prompt_policy::init(app.app_handle());
+    diagnostics::handle_args(app.app_handle(), &std::env::args().collect::<Vec<_>>());
 
     let app_handle = app.handle().clone();
     tauri::async_runtime::spawn(async move {
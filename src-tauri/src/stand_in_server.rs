@@ -0,0 +1,66 @@
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread;
+
+/// A canned response of the stand-in server.
+pub(crate) struct StandInResponse {
+    status: &'static str,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl StandInResponse {
+    /// An empty response, e.g. `503 Service Unavailable`.
+    pub fn status(status: &'static str) -> Self {
+        StandInResponse {
+            status,
+            headers: Vec::new(),
+            body: String::new(),
+        }
+    }
+
+    pub fn json(body: &str) -> Self {
+        StandInResponse {
+            body: body.to_string(),
+            ..Self::status("200 OK")
+        }
+        .header("content-type", "application/json")
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// Stands in for our servers in tests. Each request gets the next response,
+/// returns the url of the server.
+pub(crate) fn serve(responses: Vec<StandInResponse>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        for response in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 1024];
+            while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                let read = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..read]);
+            }
+
+            let mut head = format!("HTTP/1.1 {}\r\n", response.status);
+            for (name, value) in &response.headers {
+                head.push_str(&format!("{}: {}\r\n", name, value));
+            }
+            write!(
+                stream,
+                "{}content-length: {}\r\nconnection: close\r\n\r\n{}",
+                head,
+                response.body.len(),
+                response.body
+            )
+            .unwrap();
+        }
+    });
+    url
+}
@@ -1,10 +1,14 @@
 use crate::app_config::AppConfig;
 use crate::model::device::DeviceId;
+use crate::http_client::HttpClient;
+use crate::server_error::ServerError;
 use serde::Serialize;
-use tauri_plugin_http::reqwest::Client;
+use std::time::Duration;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
 
 pub struct SubscriptionManager {
-    client: Client,
+    client: HttpClient,
     device_id: DeviceId,
 }
 
@@ -16,31 +20,31 @@ struct SubscribeRequest {
 }
 
 impl SubscriptionManager {
-    pub fn new(device_id: DeviceId) -> Self {
+    pub fn new(device_id: DeviceId, client: HttpClient) -> Self {
         SubscriptionManager {
-            client: Client::new(),
+            client,
             device_id,
         }
     }
 
-    pub async fn subscribe(&self, email: Option<String>, subscribe: bool) -> Result<(), anyhow::Error> {
+    pub async fn subscribe(&self, email: Option<String>, subscribe: bool) -> Result<(), ServerError> {
         let request = SubscribeRequest {
             subscribe,
             did: self.device_id.get_hash_hex_id(),
             email,
         };
 
-        let response = self
-            .client
-            .post(format!(
-                "{}/app/v1/newsletter/subscribe",
-                AppConfig::build().get_url()
-            ))
-            .form(&request)
-            .send()
+        let url = format!(
+            "{}/app/v1/newsletter/subscribe",
+            AppConfig::build().get_url()
+        );
+        self.client
+            .send_idempotent(self.client.post(&url).timeout(REQUEST_TIMEOUT).form(&request))
             .await?;
-
-        response.error_for_status()?;
         Ok(())
     }
+
+    pub async fn unsubscribe(&self) -> Result<(), ServerError> {
+        self.subscribe(None, false).await
+    }
 }
@@ -1,14 +1,36 @@
+use crate::analytics_sink::AnalyticsSink;
+use crate::app_config::{AnalyticsSinkKind, AppConfig};
+use crate::consent::TelemetryKind;
+use crate::error_catalog::ErrorCode;
+use crate::http_client::HttpClient;
 use crate::license_manager::{LicenseStatus, ValidTypes};
 use crate::model::device::DeviceId;
-use crate::{license_manager, LicenseManagerState, SettingsManagerState};
+use crate::persistence::Persistence;
+use crate::tracking_queue::TrackingQueue;
+use crate::{analytics_sink, consent, license_manager, LicenseManagerState, TrackingState};
+use chrono::{DateTime, Utc};
 use log::{info, warn};
-use serde_json::{json, Value};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::sync::Arc;
 use std::time::Duration;
+use tauri::async_runtime::Mutex;
 use tauri::{AppHandle, Manager};
-use tauri_plugin_http::reqwest::{Client, ClientBuilder};
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5 * 60);
+const MIN_RETRY_DELAY: Duration = Duration::from_secs(30);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60 * 60);
+/// The exit waits at most this long for the queue to be sent.
+const QUIT_FLUSH_TIMEOUT: Duration = Duration::from_secs(2);
 
 pub(crate) struct Tracking {
-    client: Client,
+    sinks: Vec<Box<dyn AnalyticsSink>>,
+    routed: bool,
+    queue: TrackingQueue,
+    flush_lock: Mutex<()>,
+    /// The license state of the last event, for events, which can't wait for
+    /// the license.
+    license_state: std::sync::Mutex<String>,
     app_handle: AppHandle,
     machine_id: DeviceId,
     app_version: String,
@@ -16,35 +38,185 @@ pub(crate) struct Tracking {
     arch: String,
 }
 
-#[derive(Debug)]
+/// Everything the app tracks. All sinks share this schema, but by default each
+/// event only goes to the service, which always received it.
+#[derive(Debug, Clone)]
 pub enum Event {
+    AppStarted,
+    AppQuit,
     Install,
     ResetSettings,
     OnlyPayment,
     CancelPayment,
     DrinkReminder,
     WelcomeQuit(String),
+    Alert {
+        code: ErrorCode,
+        error: Option<String>,
+    },
+    Panic {
+        info: String,
+    },
 }
 
 impl Event {
-    fn name(&self) -> String {
+    pub fn name(&self) -> String {
         match self {
+            Event::AppStarted => String::from("app_started"),
+            Event::AppQuit => String::from("app_quit"),
             Event::Install => String::from("install"),
             Event::ResetSettings => String::from("reset_settings"),
             Event::DrinkReminder => String::from("start_session"),
             Event::OnlyPayment => String::from("only_payment"),
             Event::CancelPayment => String::from("cancel_payment"),
             Event::WelcomeQuit(state) => String::from(format!("welcome_quit_{}", state)),
+            Event::Alert { code, .. } => String::from(code.title()),
+            Event::Panic { .. } => String::from("panic"),
+        }
+    }
+
+    /// The opt-in, which is required to send the event.
+    pub fn kind(&self) -> TelemetryKind {
+        match self {
+            Event::Alert { .. } => TelemetryKind::ErrorReports,
+            Event::Panic { .. } => TelemetryKind::CrashReports,
+            _ => TelemetryKind::UsageAnalytics,
+        }
+    }
+
+    /// Usage goes to Mixpanel, the app lifecycle and errors go to Aptabase.
+    pub fn default_sink(&self) -> AnalyticsSinkKind {
+        match self {
+            Event::AppStarted | Event::AppQuit | Event::Alert { .. } | Event::Panic { .. } => {
+                AnalyticsSinkKind::Aptabase
+            }
+            _ => AnalyticsSinkKind::Mixpanel,
+        }
+    }
+
+    /// The event specific properties.
+    pub fn properties(&self) -> Map<String, Value> {
+        let properties = match self {
+            Event::Alert { code, error } => json!({
+                "code": code.code(),
+                "title": code.title(),
+                "severity": code.severity(),
+                "error": error,
+            }),
+            Event::Panic { info } => json!({ "info": info }),
+            _ => json!({}),
+        };
+        match properties {
+            Value::Object(map) => map,
+            _ => Map::new(),
         }
     }
 }
 
+/// An event together with the context of the device, as it is handed to the
+/// sinks.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrackedEvent {
+    pub name: String,
+    pub kind: TelemetryKind,
+    pub properties: Map<String, Value>,
+    pub distinct_id: String,
+    pub app_version: String,
+    pub platform: String,
+    pub arch: String,
+    pub license_state: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[cfg(test)]
+impl TrackedEvent {
+    pub fn for_tests(event: Event) -> Self {
+        TrackedEvent {
+            name: event.name(),
+            kind: event.kind(),
+            properties: event.properties(),
+            distinct_id: "test-device".to_string(),
+            app_version: "1.0.0".to_string(),
+            platform: "macos".to_string(),
+            arch: "aarch64".to_string(),
+            license_state: "trial".to_string(),
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+/// Tracks the event in the background. Events before the setup are dropped.
+pub fn track(app: &AppHandle, event: Event) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        match app.try_state::<TrackingState>() {
+            Some(tracking) => tracking.send_tracking(event).await,
+            None => warn!("tracking not ready, drop event: {:?}", event),
+        }
+    });
+}
+
+/// Sends queued events periodically. Failed batches are retried with a backoff.
+pub fn init(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut retry_delay = MIN_RETRY_DELAY;
+        loop {
+            let tracking = app.state::<TrackingState>();
+            let delay = if tracking.flush().await {
+                retry_delay = MIN_RETRY_DELAY;
+                FLUSH_INTERVAL
+            } else {
+                let delay = retry_delay;
+                retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
+                delay
+            };
+            tokio::time::sleep(delay).await;
+        }
+    });
+}
+
+/// Queues the quit and tries to send the queue for a short moment. Whatever
+/// isn't sent in time stays queued for the next start.
+pub fn track_quit(app: &AppHandle) {
+    let Some(tracking) = app.try_state::<TrackingState>() else {
+        return;
+    };
+    let license_state = tracking
+        .license_state
+        .lock()
+        .map(|state| state.clone())
+        .unwrap_or_else(|_| "unknown".to_string());
+    tracking.queue_event(Event::AppQuit, license_state);
+
+    let flushed = tauri::async_runtime::block_on(tokio::time::timeout(
+        QUIT_FLUSH_TIMEOUT,
+        tracking.flush(),
+    ));
+    if !matches!(flushed, Ok(true)) {
+        info!("tracking events stay queued until the next start");
+    }
+}
+
 impl Tracking {
-    pub fn new(device_id: &DeviceId, app_handle: &AppHandle) -> Result<Self, anyhow::Error> {
+    pub fn new(
+        device_id: &DeviceId,
+        app_handle: &AppHandle,
+        client: HttpClient,
+        persistence: Arc<Persistence>,
+        app_config: &AppConfig,
+    ) -> Result<Self, anyhow::Error> {
         let platform = tauri_plugin_os::platform().to_string();
         let arch = tauri_plugin_os::arch().to_string();
+        let sinks = analytics_sink::from_config(app_handle, app_config, &client);
+        let queue = TrackingQueue::load(persistence);
+        queue.retain_sinks(&Self::sink_names(&sinks));
         Ok(Tracking {
-            client: ClientBuilder::new().build()?,
+            sinks,
+            routed: app_config.is_analytics_routed(),
+            queue,
+            flush_lock: Mutex::new(()),
+            license_state: std::sync::Mutex::new("unknown".to_string()),
             machine_id: device_id.clone(),
             app_version: app_handle
                 .config()
@@ -57,64 +229,106 @@ impl Tracking {
         })
     }
 
+    /// Queues the event and sends the queue. Events without consent are dropped.
     pub async fn send_tracking(&self, event: Event) {
-        let license_status = self
+        if !consent::allows(event.kind()) {
+            return;
+        }
+
+        let license_state = self
             .app_handle
             .state::<LicenseManagerState>()
             .get_status(self.app_handle.app_handle(), true, false)
             .await
             .map(|data| data.status)
-            .unwrap_or_else(|err| LicenseStatus::Invalid(err));
+            .unwrap_or_else(|err| LicenseStatus::Invalid(err))
+            .to_license_status_name();
+        if let Ok(mut state) = self.license_state.lock() {
+            *state = license_state.clone();
+        }
 
-        let allow_tracking = self
-            .app_handle
-            .state::<SettingsManagerState>()
-            .get_settings()
-            .map(|s| s.user.allow_tracking)
-            .unwrap_or(false);
-        if allow_tracking {
-            info!("send event: {:?}", event);
-            let event_data = json!([{
-                "event": event.name(),
-                "properties": {
-                    "token": "21c5f5354133116affaafe40b4d316db",
-                    "app_version": self.app_version,
-                    "platform": self.platform,
-                    "arch": self.arch,
-                    "distinct_id": self.machine_id.get_hash_hex_id(),
-                    "license_state": license_status.to_license_status_name()
+        if self.queue_event(event, license_state) {
+            self.flush().await;
+        }
+    }
+
+    /// Adds the event to the persisted queue. Returns false, if the event was
+    /// dropped.
+    fn queue_event(&self, event: Event, license_state: String) -> bool {
+        if !consent::allows(event.kind()) {
+            return false;
+        }
+        let sink_names = self.sinks_for(&event);
+        if sink_names.is_empty() {
+            return false;
+        }
+
+        info!("queue event: {:?}", event);
+        let tracked_event = TrackedEvent {
+            name: event.name(),
+            kind: event.kind(),
+            properties: event.properties(),
+            distinct_id: self.machine_id.get_hash_hex_id(),
+            app_version: self.app_version.clone(),
+            platform: self.platform.clone(),
+            arch: self.arch.clone(),
+            license_state,
+            timestamp: Utc::now(),
+        };
+        self.queue.push(tracked_event, sink_names);
+        true
+    }
+
+    /// Sends the oldest queued events to every sink, which didn't accept them
+    /// yet. Returns false, if a sink failed.
+    pub async fn flush(&self) -> bool {
+        let _guard = self.flush_lock.lock().await;
+        // the consent may have been withdrawn since the events were queued
+        self.queue.retain_allowed(consent::allows);
+        if self.queue.is_empty() {
+            return true;
+        }
+
+        let batch = self.queue.batch(Utc::now());
+        let mut success = true;
+        for sink in &self.sinks {
+            let (ids, events): (Vec<u64>, Vec<TrackedEvent>) = batch
+                .iter()
+                .filter(|queued| queued.pending_sinks.iter().any(|name| name == sink.name()))
+                .map(|queued| (queued.id, queued.event.clone()))
+                .unzip();
+            if events.is_empty() {
+                continue;
+            }
+            match sink.send_batch(&events).await {
+                Ok(_) => self.queue.mark_sent(sink.name(), &ids),
+                Err(e) => {
+                    warn!(
+                        "error sending {} tracking events to {}: {:?}",
+                        events.len(),
+                        sink.name(),
+                        e
+                    );
+                    success = false;
                 }
-            }]);
-            Self::send(&event_data, &self.client)
-                .await
-                .unwrap_or_else(|e| {
-                    warn!("error sending tracking event: {:?}", e);
-                    ()
-                })
+            }
         }
+        success
     }
 
-    async fn send(event_data: &Value, client: &Client) -> Result<(), anyhow::Error> {
-        let res = client
-            .post("https://api.mixpanel.com/track?ip=1&verbose=1")
-            .header("Accept", "text/plain")
-            .header("Content-Type", "application/json")
-            .json(&event_data)
-            .timeout(Duration::from_secs(10))
-            .send()
-            .await?;
-
-        let json = res.error_for_status()?.json::<Value>().await?;
-        let status = json
-            .get("status")
-            .map(|v| v.as_u64().unwrap_or(0))
-            .ok_or(anyhow::anyhow!("expected status from tracking request"))?;
-
-        if status == 1 {
-            Ok(())
-        } else {
-            Err(anyhow::anyhow!("error sending tracking event: {}", json))
+    fn sinks_for(&self, event: &Event) -> Vec<String> {
+        let names = Self::sink_names(&self.sinks);
+        if !self.routed {
+            return names;
         }
+        names
+            .into_iter()
+            .filter(|name| name == event.default_sink().name())
+            .collect()
+    }
+
+    fn sink_names(sinks: &[Box<dyn AnalyticsSink>]) -> Vec<String> {
+        sinks.iter().map(|sink| sink.name().to_string()).collect()
     }
 
     pub fn device_id(&self) -> DeviceId {
@@ -138,6 +352,24 @@ impl LicenseConverter for license_manager::LicenseStatus {
             LicenseStatus::Invalid(reason) => {
                 format!("invalid_{}", reason)
             }
+            LicenseStatus::Unknown(_) => "unknown".to_string(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_sink() {
+        let alert = Event::Alert {
+            code: ErrorCode::SettingsUnavailable,
+            error: None,
+        };
+        assert_eq!(alert.name(), "Can't open settings");
+        assert_eq!(alert.default_sink(), AnalyticsSinkKind::Aptabase);
+        assert_eq!(Event::AppQuit.default_sink(), AnalyticsSinkKind::Aptabase);
+        assert_eq!(Event::Install.default_sink(), AnalyticsSinkKind::Mixpanel);
+    }
+}
@@ -0,0 +1,180 @@
+use crate::consent::TelemetryKind;
+use crate::persistence::{Persisted, Persistence};
+use crate::tracking::TrackedEvent;
+use chrono::{DateTime, Duration, Utc};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+const MAX_QUEUE_SIZE: usize = 500;
+const MAX_EVENT_AGE_DAYS: i64 = 14;
+const BATCH_SIZE: usize = 50;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct QueuedEvent {
+    pub id: u64,
+    pub event: TrackedEvent,
+    /// The sinks, which didn't accept the event yet.
+    pub pending_sinks: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct TrackingQueueStore {
+    events: Vec<QueuedEvent>,
+}
+
+impl Persisted for TrackingQueueStore {
+    const FILE_NAME: &'static str = "mm-tracking-queue.json";
+    const ROOT_KEY: &'static str = "queue";
+}
+
+/// Tracking events, which are not sent yet. The queue survives restarts, so
+/// events fired while offline are sent later.
+pub(crate) struct TrackingQueue {
+    persistence: Arc<Persistence>,
+    store: Mutex<TrackingQueueStore>,
+}
+
+impl TrackingQueue {
+    pub fn load(persistence: Arc<Persistence>) -> Self {
+        let store = persistence
+            .load::<TrackingQueueStore>()
+            .unwrap_or_else(|err| {
+                warn!("tracking queue not readable, start empty: {:?}", err);
+                None
+            })
+            .unwrap_or_default();
+        if !store.events.is_empty() {
+            info!("{} tracking events queued", store.events.len());
+        }
+        TrackingQueue {
+            persistence,
+            store: Mutex::new(store),
+        }
+    }
+
+    pub fn push(&self, event: TrackedEvent, sinks: Vec<String>) {
+        let mut store = self.lock();
+        let id = store.events.iter().map(|queued| queued.id).max().unwrap_or(0) + 1;
+        store.events.push(QueuedEvent {
+            id,
+            event,
+            pending_sinks: sinks,
+        });
+        if store.events.len() > MAX_QUEUE_SIZE {
+            let dropped = store.events.len() - MAX_QUEUE_SIZE;
+            warn!("tracking queue is full, drop {} oldest events", dropped);
+            store.events.drain(..dropped);
+        }
+        self.save(&store);
+    }
+
+    /// The oldest events. Expired events are dropped.
+    pub fn batch(&self, now: DateTime<Utc>) -> Vec<QueuedEvent> {
+        let mut store = self.lock();
+        let count = store.events.len();
+        store
+            .events
+            .retain(|queued| now - queued.event.timestamp < Duration::days(MAX_EVENT_AGE_DAYS));
+        if store.events.len() < count {
+            info!("drop {} expired tracking events", count - store.events.len());
+            self.save(&store);
+        }
+        store.events.iter().take(BATCH_SIZE).cloned().collect()
+    }
+
+    /// Removes the sink from the events. Events accepted by all sinks are removed.
+    pub fn mark_sent(&self, sink: &str, ids: &[u64]) {
+        let mut store = self.lock();
+        for queued in store.events.iter_mut().filter(|queued| ids.contains(&queued.id)) {
+            queued.pending_sinks.retain(|pending| pending != sink);
+        }
+        store.events.retain(|queued| !queued.pending_sinks.is_empty());
+        self.save(&store);
+    }
+
+    /// Forgets sinks, which are no longer configured.
+    pub fn retain_sinks(&self, sinks: &[String]) {
+        let mut store = self.lock();
+        for queued in store.events.iter_mut() {
+            queued.pending_sinks.retain(|pending| sinks.contains(pending));
+        }
+        store.events.retain(|queued| !queued.pending_sinks.is_empty());
+        self.save(&store);
+    }
+
+    /// Drops the events, which are no longer allowed to be sent.
+    pub fn retain_allowed(&self, allowed: impl Fn(TelemetryKind) -> bool) {
+        let mut store = self.lock();
+        let count = store.events.len();
+        store.events.retain(|queued| allowed(queued.event.kind));
+        if store.events.len() < count {
+            info!("consent withdrawn, drop {} tracking events", count - store.events.len());
+            self.save(&store);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().events.is_empty()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, TrackingQueueStore> {
+        self.store.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn save(&self, store: &TrackingQueueStore) {
+        self.persistence
+            .save(store)
+            .unwrap_or_else(|err| error!("unable to save tracking queue: {:?}", err));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracking::Event;
+
+    fn queue() -> (tempfile::TempDir, TrackingQueue) {
+        let dir = tempfile::tempdir().unwrap();
+        let persistence = Arc::new(Persistence::with_dir(dir.path().to_path_buf()).unwrap());
+        (dir, TrackingQueue::load(persistence))
+    }
+
+    fn sinks() -> Vec<String> {
+        vec!["mixpanel".to_string(), "file".to_string()]
+    }
+
+    #[test]
+    fn test_event_is_removed_when_all_sinks_accepted_it() {
+        let (dir, queue) = queue();
+        queue.push(TrackedEvent::for_tests(Event::Install), sinks());
+
+        let batch = queue.batch(Utc::now());
+        queue.mark_sent("file", &[batch[0].id]);
+        let reloaded =
+            TrackingQueue::load(Arc::new(Persistence::with_dir(dir.path().to_path_buf()).unwrap()));
+        assert_eq!(reloaded.batch(Utc::now())[0].pending_sinks, vec!["mixpanel"]);
+
+        queue.mark_sent("mixpanel", &[batch[0].id]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_queue_drops_oldest_and_expired_events() {
+        let (_dir, queue) = queue();
+        queue.lock().events = (1..=MAX_QUEUE_SIZE as u64)
+            .map(|id| QueuedEvent {
+                id,
+                event: TrackedEvent::for_tests(Event::DrinkReminder),
+                pending_sinks: sinks(),
+            })
+            .collect();
+        queue.push(TrackedEvent::for_tests(Event::DrinkReminder), sinks());
+        queue.push(TrackedEvent::for_tests(Event::DrinkReminder), sinks());
+        assert_eq!(queue.batch(Utc::now())[0].id, 3);
+
+        let later = Utc::now() + Duration::days(MAX_EVENT_AGE_DAYS + 1);
+        assert!(queue.batch(later).is_empty());
+        assert!(queue.is_empty());
+    }
+}
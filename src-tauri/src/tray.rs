@@ -1,7 +1,10 @@
 use crate::alert::Alert;
 use crate::countdown_timer::{CountdownEvent, CountdownTimer, PauseOrigin, TimerStatus};
+use crate::error_catalog::ErrorCode;
 use crate::model::settings::SettingsTabs;
 use crate::pretty_time::PrettyTime;
+#[cfg(not(feature = "fullversion"))]
+use crate::update_manager::UpdateReady;
 use crate::{dashboard_window, feedback_window, session_window, settings_window, updater_window, CountdownTimerState};
 use anyhow::anyhow;
 use std::time::Duration;
@@ -16,6 +19,8 @@ use tauri::{
 use tauri_specta::Event;
 
 const TRAY_ID: &'static str = "tray";
+#[cfg(not(feature = "fullversion"))]
+const UPDATE_READY_TEXT: &str = "Update ready – restart to apply";
 
 pub fn create_tray(main_app: &AppHandle<Wry>) -> tauri::Result<()> {
     let menu_status = MenuItem::with_id(main_app, "dashboard", "Dashboard", true, None::<&str>)?;
@@ -26,6 +31,15 @@ pub fn create_tray(main_app: &AppHandle<Wry>) -> tauri::Result<()> {
         true,
         None::<&str>,
     )?;
+    #[cfg(not(feature = "fullversion"))]
+    let menu_updater = IconMenuItem::with_id(
+        main_app,
+        "updater",
+        "Check for updates ...",
+        true,
+        None,
+        None::<&str>,
+    )?;
 
     let menu = Menu::with_items(
         main_app,
@@ -52,14 +66,7 @@ pub fn create_tray(main_app: &AppHandle<Wry>) -> tauri::Result<()> {
             &PredefinedMenuItem::separator(main_app)?,
             &IconMenuItem::with_id(main_app, "about", "About ...", true, None, None::<&str>)?,
             #[cfg(not(feature = "fullversion"))]
-            &IconMenuItem::with_id(
-                main_app,
-                "updater",
-                "Check for updates ...",
-                true,
-                None,
-                None::<&str>,
-            )?,
+            &menu_updater,
             &IconMenuItem::with_id(
                 main_app,
                 "feedback",
@@ -82,12 +89,7 @@ pub fn create_tray(main_app: &AppHandle<Wry>) -> tauri::Result<()> {
         .on_menu_event(move |app, event| match event.id.as_ref() {
             "dashboard" => {
                 dashboard_window::show(app.app_handle()).unwrap_or_else(|e| {
-                    app.alert(
-                        "Error while showing dashboard",
-                        "I am sorry, we are unable to show the dashboard. Please try again later.",
-                        Some(e),
-                        false,
-                    )
+                    app.alert(ErrorCode::DashboardUnavailable, Some(e), false)
                 });
             }
             "start" => {
@@ -97,23 +99,13 @@ pub fn create_tray(main_app: &AppHandle<Wry>) -> tauri::Result<()> {
                     timer.restart();
 
                     session_window::show_session(app_handle.app_handle(), None).await.unwrap_or_else(|e| {
-                        app_handle.alert(
-                            "Error while starting the session",
-                            "I am sorry, we are unable to start the session.",
-                            Some(e),
-                            false,
-                        );
+                        app_handle.alert(ErrorCode::SessionStartFailed, Some(e), false);
                     });
                 });
             }
             "settings" => {
                 settings_window::show(app, SettingsTabs::Session).unwrap_or_else(|e| {
-                    app.alert(
-                        "Error while opening settings",
-                        "I am sorry, we are unable to open up the settings.",
-                        Some(anyhow!(e)),
-                        false,
-                    );
+                    app.alert(ErrorCode::SettingsUnavailable, Some(anyhow!(e)), false);
                 });
             }
             "timer_control" => {
@@ -126,33 +118,21 @@ pub fn create_tray(main_app: &AppHandle<Wry>) -> tauri::Result<()> {
             }
             #[cfg(not(feature = "fullversion"))]
             "updater" => {
+                if updater_window::install_downloaded_on_request(app.app_handle()) {
+                    return;
+                }
                 updater_window::show(app.app_handle()).unwrap_or_else(|e| {
-                    app.alert(
-                        "Error while opening updater",
-                        "I am sorry, we are unable to open the updater.",
-                        Some(anyhow!(e)),
-                        false,
-                    );
+                    app.alert(ErrorCode::UpdaterUnavailable, Some(anyhow!(e)), false);
                 });
             }
             "about" => {
                 settings_window::show(app, SettingsTabs::About).unwrap_or_else(|e| {
-                    app.alert(
-                        "Error while opening settings",
-                        "I am sorry, we are unable to open up the settings.",
-                        Some(anyhow!(e)),
-                        false,
-                    );
+                    app.alert(ErrorCode::SettingsUnavailable, Some(anyhow!(e)), false);
                 });
             }
             "feedback" => {
                 feedback_window::show(app).unwrap_or_else(|e| {
-                    app.alert(
-                        "Error while opening settings",
-                        "I am sorry, we are unable to open up feedback.",
-                        Some(anyhow!(e)),
-                        false,
-                    );
+                    app.alert(ErrorCode::FeedbackUnavailable, Some(anyhow!(e)), false);
                 });
             }
             "quit" => {
@@ -175,12 +155,7 @@ pub fn create_tray(main_app: &AppHandle<Wry>) -> tauri::Result<()> {
         menu_timer_control
             .set_text(timer_control_text)
             .unwrap_or_else(|err| {
-                app_handle.alert(
-                    "Can't set timer in tray",
-                    "Unable to update tray",
-                    Some(anyhow::anyhow!(err)),
-                    true,
-                );
+                app_handle.alert(ErrorCode::TrayUpdateFailed, Some(anyhow::anyhow!(err)), true);
             });
 
         menu_status
@@ -195,6 +170,17 @@ pub fn create_tray(main_app: &AppHandle<Wry>) -> tauri::Result<()> {
             .ok();
     });
 
+    #[cfg(not(feature = "fullversion"))]
+    {
+        let app_handle_update_ready = main_app.app_handle().clone();
+        UpdateReady::listen(main_app.app_handle(), move |event| {
+            log::info!("version {} is ready to install", event.payload.version);
+            show_update_ready(&app_handle_update_ready, &menu_updater)
+                .map_err(|e| log::error!("Failed to show ready update in tray: {}", e))
+                .ok();
+        });
+    }
+
     Ok(())
 }
 
@@ -226,6 +212,15 @@ pub fn update_tray_title(app_handle: &AppHandle<Wry>, status: TimerStatus) -> ta
     Ok(())
 }
 
+#[cfg(not(feature = "fullversion"))]
+fn show_update_ready(app_handle: &AppHandle<Wry>, menu_updater: &IconMenuItem<Wry>) -> tauri::Result<()> {
+    menu_updater.set_text(UPDATE_READY_TEXT)?;
+    if let Some(tray) = app_handle.tray_by_id(TRAY_ID) {
+        tray.set_tooltip(Some(UPDATE_READY_TEXT))?;
+    }
+    Ok(())
+}
+
 fn tray_icon(app: &AppHandle<Wry>) -> tauri::Result<Image<'_>> {
     let image_path = if cfg!(target_os = "windows") {
         "icons/justdrink-glass-tray-50.png"
@@ -0,0 +1,698 @@
+use crate::app_config::AppConfig;
+use crate::model::device::DeviceId;
+use crate::model::settings::UpdateChannel;
+use crate::persistence::{Persisted, Persistence};
+use crate::{audit_log, SettingsManagerState, SettingsSystemState};
+use anyhow::{anyhow, Context};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use minisign_verify::{PublicKey, Signature};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tauri::async_runtime::Mutex;
+use tauri::http::{HeaderMap, HeaderValue};
+use tauri::{AppHandle, Manager, Url};
+use tauri_plugin_updater::{Update, Updater, UpdaterExt};
+use tauri_specta::Event;
+
+const ARTIFACT_DIR: &str = "updates";
+/// The installers of the running and of the previous version.
+const KEPT_ARTIFACTS: usize = 2;
+const CHANNEL_HEADER: &str = "x-update-channel";
+const PINNED_VERSION_HEADER: &str = "x-pinned-version";
+const ROLLOUT_BUCKET_HEADER: &str = "x-rollout-bucket";
+/// The share of devices in percent, which get a release. Devices in a lower
+/// bucket get it first.
+const ROLLOUT_PERCENTAGE_KEY: &str = "rollout_percentage";
+
+impl UpdateChannel {
+    fn name(&self) -> &'static str {
+        match self {
+            UpdateChannel::Stable => "stable",
+            UpdateChannel::Beta => "beta",
+            UpdateChannel::Nightly => "nightly",
+        }
+    }
+}
+
+/// An installer, which was downloaded by the updater.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct UpdateArtifact {
+    version: String,
+    file_name: String,
+    installed_at: DateTime<Utc>,
+    /// The signature announced with the installer, it is verified again before
+    /// a rollback. Installers kept without one can't be restored.
+    #[serde(default)]
+    signature: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct UpdateArtifacts {
+    artifacts: Vec<UpdateArtifact>,
+}
+
+impl Persisted for UpdateArtifacts {
+    const FILE_NAME: &'static str = "mm-update-artifacts.json";
+    const ROOT_KEY: &'static str = "updates";
+}
+
+/// What the updater window shows.
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+pub struct UpdateInfo {
+    pub(crate) channel: UpdateChannel,
+    pub(crate) current_version: String,
+    /// The offered version, none if the app is up to date.
+    pub(crate) version: Option<String>,
+    /// The release date of the offered version as RFC 3339.
+    pub(crate) date: Option<String>,
+    /// The release notes of the offered version as markdown.
+    pub(crate) notes: Option<String>,
+    pub(crate) pinned_version: Option<String>,
+    /// The previous version, if its installer was kept.
+    pub(crate) rollback_version: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type, Event)]
+pub enum UpdateProgress {
+    Started { content_length: Option<u32> },
+    Progress { chunk_length: u32 },
+    Finished,
+}
+
+/// An update was downloaded in the background and is installed on quit or
+/// when the user is idle.
+#[derive(Serialize, Deserialize, Debug, Clone, Type, Event)]
+pub struct UpdateReady {
+    pub(crate) version: String,
+}
+
+/// A downloaded update, which waits for its installation.
+struct ReadyUpdate {
+    update: Box<dyn ReadyInstaller>,
+    bytes: Vec<u8>,
+}
+
+/// The downloaded update, as far as its installation needs it. Tests install
+/// without an update server.
+trait ReadyInstaller: Send {
+    fn version(&self) -> &str;
+
+    /// Keeps the installer for a rollback and runs it.
+    fn install(&self, manager: &UpdateManager, bytes: Vec<u8>) -> Result<(), anyhow::Error>;
+}
+
+impl ReadyInstaller for Update {
+    fn version(&self) -> &str {
+        &self.version
+    }
+
+    fn install(&self, manager: &UpdateManager, bytes: Vec<u8>) -> Result<(), anyhow::Error> {
+        manager
+            .keep_artifact(self, &bytes)
+            .unwrap_or_else(|err| warn!("unable to keep installer: {:?}", err));
+        Update::install(self, bytes)?;
+        Ok(())
+    }
+}
+
+/// What happened to the update downloaded in the background.
+#[derive(Debug, PartialEq)]
+pub enum ReadyInstall {
+    Installed(String),
+    /// A session is showing, the version is installed later.
+    Waiting(String),
+    NotDownloaded,
+}
+
+/// Checks the selected channel, installs updates and restores the previous
+/// version with its kept installer.
+pub(crate) struct UpdateManager {
+    persistence: Arc<Persistence>,
+    artifact_dir: PathBuf,
+    rollout_bucket: u8,
+    pending: Mutex<Option<Update>>,
+    /// Not async, it is installed while the app exits.
+    ready: std::sync::Mutex<Option<ReadyUpdate>>,
+}
+
+impl UpdateManager {
+    pub fn new(persistence: Arc<Persistence>, device_id: &DeviceId) -> Self {
+        UpdateManager {
+            artifact_dir: persistence.dir().join(ARTIFACT_DIR),
+            persistence,
+            rollout_bucket: device_id.rollout_bucket(),
+            pending: Mutex::new(None),
+            ready: std::sync::Mutex::new(None),
+        }
+    }
+
+    /// Checks for an update and keeps it for the installation. Releases, which
+    /// aren't rolled out to this device yet, aren't offered.
+    pub async fn check(&self, app: &AppHandle) -> Result<Option<String>, anyhow::Error> {
+        let update = updater(app, None, self.rollout_bucket)?
+            .check()
+            .await?
+            .filter(|update| {
+                let percentage = rollout_percentage(&update.raw_json);
+                let rolled_out = is_rolled_out(self.rollout_bucket, percentage);
+                if !rolled_out {
+                    info!(
+                        "version {} is rolled out to {:?}%, not to bucket {}",
+                        update.version, percentage, self.rollout_bucket
+                    );
+                }
+                rolled_out
+            });
+        let version = update.as_ref().map(|update| update.version.clone());
+        debug!("update check finished, offered version: {:?}", version);
+        *self.pending.lock().await = update;
+        Ok(version)
+    }
+
+    pub async fn info(&self, app: &AppHandle) -> UpdateInfo {
+        let user = app
+            .state::<SettingsManagerState>()
+            .get_settings()
+            .map(|settings| settings.user);
+        let pending = self.pending.lock().await;
+        UpdateInfo {
+            channel: user
+                .as_ref()
+                .map(|user| user.update_channel)
+                .unwrap_or_default(),
+            current_version: app.package_info().version.to_string(),
+            version: pending.as_ref().map(|update| update.version.clone()),
+            date: pending
+                .as_ref()
+                .and_then(|update| update.date)
+                .and_then(|date| DateTime::from_timestamp(date.unix_timestamp(), 0))
+                .map(|date| date.to_rfc3339()),
+            notes: pending.as_ref().and_then(|update| update.body.clone()),
+            pinned_version: user.and_then(|user| user.pinned_version),
+            rollback_version: self
+                .previous_artifact(&app.package_info().version)
+                .map(|artifact| artifact.version),
+        }
+    }
+
+    /// Downloads and installs the checked update. The installer is kept, so this
+    /// version can be restored after the next update.
+    pub async fn install(&self, app: &AppHandle) -> Result<(), anyhow::Error> {
+        let pending = self.pending.lock().await;
+        let update = pending
+            .as_ref()
+            .ok_or_else(|| anyhow!("no update available"))?;
+
+        let bytes = download(app, update).await?;
+        self.keep_artifact(update, &bytes)
+            .unwrap_or_else(|err| warn!("unable to keep installer: {:?}", err));
+        info!("install version {}", update.version);
+        update.install(bytes)?;
+        Ok(())
+    }
+
+    /// Downloads the checked update in the background, the signature is verified
+    /// by the download. Returns the downloaded version.
+    pub async fn download(&self, app: &AppHandle) -> Result<String, anyhow::Error> {
+        let mut pending = self.pending.lock().await;
+        let update = pending
+            .take()
+            .ok_or_else(|| anyhow!("no update available"))?;
+        if self.ready_version().as_deref() == Some(update.version.as_str()) {
+            debug!("version {} is already downloaded", update.version);
+            return Ok(update.version);
+        }
+
+        let bytes = download(app, &update).await?;
+        let version = update.version.clone();
+        info!("version {} downloaded, {} bytes", version, bytes.len());
+        *self.lock_ready() = Some(ReadyUpdate {
+            update: Box::new(update),
+            bytes,
+        });
+        emit(
+            app,
+            UpdateReady {
+                version: version.clone(),
+            },
+        );
+        Ok(version)
+    }
+
+    /// The version downloaded in the background.
+    pub fn ready_version(&self) -> Option<String> {
+        self.lock_ready()
+            .as_ref()
+            .map(|ready| ready.update.version().to_string())
+    }
+
+    /// Installs the update downloaded in the background, unless a session is
+    /// showing. The update isn't installed in the middle of a session.
+    pub fn install_ready(&self, session_visible: bool) -> Result<ReadyInstall, anyhow::Error> {
+        if session_visible {
+            return Ok(match self.ready_version() {
+                Some(version) => {
+                    debug!("a session is showing, version {} waits", version);
+                    ReadyInstall::Waiting(version)
+                }
+                None => ReadyInstall::NotDownloaded,
+            });
+        }
+        let Some(ready) = self.lock_ready().take() else {
+            return Ok(ReadyInstall::NotDownloaded);
+        };
+        let version = ready.update.version().to_string();
+        info!("install downloaded version {}", version);
+        ready.update.install(self, ready.bytes)?;
+        Ok(ReadyInstall::Installed(version))
+    }
+
+    /// Installs the kept installer of the previous version, after its signature
+    /// was verified again. The current version is skipped, so it isn't offered
+    /// again right away.
+    pub async fn rollback(&self, app: &AppHandle) -> Result<String, anyhow::Error> {
+        let current = app.package_info().version.clone();
+        let artifact = self
+            .previous_artifact(&current)
+            .ok_or_else(|| anyhow!("the installer of the previous version wasn't kept"))?;
+        let signature = artifact
+            .signature
+            .clone()
+            .ok_or_else(|| anyhow!("the installer of the previous version isn't signed"))?;
+        let bytes = fs::read(self.artifact_dir.join(&artifact.file_name))
+            .with_context(|| format!("unable to read installer {}", artifact.file_name))?;
+        verify_installer(&bytes, &signature, &updater_public_key(app)?)
+            .with_context(|| format!("installer {} can't be trusted", artifact.file_name))?;
+
+        // the updater only installs releases it got from the server, so the
+        // kept version is pinned and installed like it was offered
+        let mut update = updater(app, Some(&artifact.version), self.rollout_bucket)?
+            .check()
+            .await?
+            .ok_or_else(|| {
+                anyhow!(
+                    "the update server doesn't offer version {}",
+                    artifact.version
+                )
+            })?;
+        update.version = artifact.version.clone();
+        update.signature = signature;
+
+        info!("roll back from {} to {}", current, artifact.version);
+        app.state::<SettingsSystemState>()
+            .lock()
+            .map_err(|err| anyhow!(err.to_string()))?
+            .skip_version(current.to_string());
+        update.install(bytes)?;
+        Ok(artifact.version)
+    }
+
+    fn keep_artifact(&self, update: &Update, bytes: &[u8]) -> Result<(), anyhow::Error> {
+        let version = update.version.as_str();
+        let installer_name: String = update
+            .download_url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .unwrap_or("installer")
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+            .collect();
+        let file_name = format!("{}-{}", version, installer_name);
+        fs::create_dir_all(&self.artifact_dir)?;
+        fs::write(self.artifact_dir.join(&file_name), bytes)?;
+
+        let mut stored = self
+            .persistence
+            .load::<UpdateArtifacts>()?
+            .unwrap_or_default();
+        stored
+            .artifacts
+            .retain(|artifact| artifact.version != version);
+        stored.artifacts.push(UpdateArtifact {
+            version: version.to_string(),
+            file_name,
+            installed_at: Utc::now(),
+            signature: Some(update.signature.clone()),
+        });
+        let outdated = stored.artifacts.len().saturating_sub(KEPT_ARTIFACTS);
+        for artifact in stored.artifacts.drain(..outdated) {
+            fs::remove_file(self.artifact_dir.join(&artifact.file_name))
+                .unwrap_or_else(|err| warn!("unable to remove installer: {:?}", err));
+        }
+        self.persistence.save(&stored)
+    }
+
+    fn lock_ready(&self) -> std::sync::MutexGuard<'_, Option<ReadyUpdate>> {
+        self.ready.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn previous_artifact(&self, current: &Version) -> Option<UpdateArtifact> {
+        let stored = self
+            .persistence
+            .load::<UpdateArtifacts>()
+            .unwrap_or_else(|err| {
+                warn!("kept installers unknown: {:?}", err);
+                None
+            })?;
+        previous_artifact(&stored.artifacts, current).filter(|artifact| {
+            artifact.signature.is_some() && self.artifact_dir.join(&artifact.file_name).exists()
+        })
+    }
+}
+
+/// The newest kept installer, which is older than the running version.
+fn previous_artifact(artifacts: &[UpdateArtifact], current: &Version) -> Option<UpdateArtifact> {
+    artifacts
+        .iter()
+        .filter_map(|artifact| {
+            Version::parse(&artifact.version)
+                .ok()
+                .filter(|version| version < current)
+                .map(|version| (version, artifact))
+        })
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, artifact)| artifact.clone())
+}
+
+/// Whether the release is offered. A pinned version is offered, even if it is
+/// older than the running one.
+fn is_offered(
+    current: &Version,
+    release: &Version,
+    pinned: Option<&Version>,
+    skipped: &[String],
+) -> bool {
+    if skipped.contains(&release.to_string()) {
+        return false;
+    }
+    match pinned {
+        Some(pinned) => release == pinned && release != current,
+        None => release > current,
+    }
+}
+
+/// The rollout percentage of the update manifest, all devices get the release
+/// without one.
+fn rollout_percentage(manifest: &serde_json::Value) -> Option<u64> {
+    manifest
+        .get(ROLLOUT_PERCENTAGE_KEY)
+        .and_then(|percentage| percentage.as_u64())
+}
+
+fn is_rolled_out(rollout_bucket: u8, percentage: Option<u64>) -> bool {
+    percentage.is_none_or(|percentage| u64::from(rollout_bucket) < percentage)
+}
+
+/// The updater for the selected channel. A rollback pins its version and
+/// accepts whatever the server offers.
+fn updater(
+    app: &AppHandle,
+    rollback_version: Option<&str>,
+    rollout_bucket: u8,
+) -> Result<Updater, anyhow::Error> {
+    let any_release = rollback_version.is_some();
+    let user = app
+        .state::<SettingsManagerState>()
+        .get_settings()
+        .map(|settings| settings.user);
+    let channel = user
+        .as_ref()
+        .map(|user| user.update_channel)
+        .unwrap_or_default();
+    let pinned = rollback_version
+        .map(str::to_string)
+        .or_else(|| user.and_then(|user| user.pinned_version))
+        .filter(|version| !version.trim().is_empty())
+        .and_then(|version| {
+            Version::parse(version.trim())
+                .inspect_err(|err| warn!("ignore pinned version '{}': {}", version, err))
+                .ok()
+        });
+    let skipped = app
+        .state::<SettingsSystemState>()
+        .lock()
+        .map(|settings_system| settings_system.settings().skipped_versions)
+        .unwrap_or_default();
+
+    let mut headers = HeaderMap::new();
+    // older update servers only know pre-releases
+    headers.insert(
+        "prerelease",
+        HeaderValue::from_static(if channel == UpdateChannel::Stable {
+            "false"
+        } else {
+            "true"
+        }),
+    );
+    headers.insert(CHANNEL_HEADER, HeaderValue::from_static(channel.name()));
+    headers.insert(
+        ROLLOUT_BUCKET_HEADER,
+        HeaderValue::from(u16::from(rollout_bucket)),
+    );
+    if let Some(pinned) = &pinned {
+        headers.insert(
+            PINNED_VERSION_HEADER,
+            HeaderValue::from_str(&pinned.to_string())?,
+        );
+    }
+    debug!(
+        "check channel {}, pinned: {:?}, skipped: {:?}",
+        channel.name(),
+        pinned,
+        skipped
+    );
+
+    let updater_url = AppConfig::build().get_updater_url();
+    let endpoints = match &updater_url {
+        Some(updater_url) => vec![updater_url.clone()],
+        None => updater_endpoints(app),
+    };
+    for endpoint in endpoints {
+        audit_log::record_headers("GET", &endpoint, &headers);
+    }
+
+    let mut builder =
+        app.updater_builder()
+            .headers(headers)
+            .version_comparator(move |current, release| {
+                any_release || is_offered(&current, &release.version, pinned.as_ref(), &skipped)
+            });
+    if let Some(updater_url) = updater_url {
+        debug!("use custom updater endpoint {updater_url}");
+        builder = builder.endpoints(vec![Url::parse(&updater_url)?])?;
+    }
+    Ok(builder.build()?)
+}
+
+/// The endpoints of the updater config, which are tried in order.
+fn updater_endpoints(app: &AppHandle) -> Vec<String> {
+    app.config()
+        .plugins
+        .0
+        .get("updater")
+        .and_then(|updater| updater.get("endpoints"))
+        .and_then(|endpoints| endpoints.as_array())
+        .map(|endpoints| {
+            endpoints
+                .iter()
+                .filter_map(|endpoint| endpoint.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The public key of the updater config, base64 encoded like the signatures.
+fn updater_public_key(app: &AppHandle) -> Result<String, anyhow::Error> {
+    app.config()
+        .plugins
+        .0
+        .get("updater")
+        .and_then(|updater| updater.get("pubkey"))
+        .and_then(|pubkey| pubkey.as_str())
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("no updater public key configured"))
+}
+
+/// Verifies an installer the same way the updater does after a download.
+fn verify_installer(bytes: &[u8], signature: &str, public_key: &str) -> Result<(), anyhow::Error> {
+    let public_key = PublicKey::decode(&String::from_utf8(
+        BASE64_STANDARD.decode(public_key.trim())?,
+    )?)
+    .map_err(|err| anyhow!("invalid updater public key: {}", err))?;
+    let signature = Signature::decode(&String::from_utf8(
+        BASE64_STANDARD.decode(signature.trim())?,
+    )?)
+    .map_err(|err| anyhow!("invalid installer signature: {}", err))?;
+    public_key
+        .verify(bytes, &signature, true)
+        .map_err(|err| anyhow!("installer signature does not match: {}", err))
+}
+
+/// Downloads the installer and reports the progress.
+async fn download(app: &AppHandle, update: &Update) -> Result<Vec<u8>, anyhow::Error> {
+    let mut started = false;
+    let bytes = update
+        .download(
+            |chunk_length, content_length| {
+                if !started {
+                    started = true;
+                    emit(
+                        app,
+                        UpdateProgress::Started {
+                            content_length: content_length.map(|length| length as u32),
+                        },
+                    );
+                }
+                emit(
+                    app,
+                    UpdateProgress::Progress {
+                        chunk_length: chunk_length as u32,
+                    },
+                );
+            },
+            || emit(app, UpdateProgress::Finished),
+        )
+        .await?;
+    Ok(bytes)
+}
+
+fn emit<E: Event + Serialize + Clone>(app: &AppHandle, event: E) {
+    event
+        .emit(app)
+        .unwrap_or_else(|err| warn!("unable to emit update event: {:?}", err));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    struct FakeUpdate {
+        version: String,
+        installed: Arc<AtomicBool>,
+    }
+
+    impl ReadyInstaller for FakeUpdate {
+        fn version(&self) -> &str {
+            &self.version
+        }
+
+        fn install(&self, _manager: &UpdateManager, _bytes: Vec<u8>) -> Result<(), anyhow::Error> {
+            self.installed.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    fn version(version: &str) -> Version {
+        Version::parse(version).unwrap()
+    }
+
+    #[test]
+    fn test_pinned_and_skipped_versions() {
+        let current = version("1.4.0");
+        assert!(is_offered(&current, &version("1.5.0"), None, &[]));
+        assert!(!is_offered(&current, &version("1.3.0"), None, &[]));
+        assert!(!is_offered(
+            &current,
+            &version("1.5.0"),
+            None,
+            &["1.5.0".to_string()]
+        ));
+
+        let pinned = version("1.3.2");
+        assert!(is_offered(&current, &pinned, Some(&pinned), &[]));
+        assert!(!is_offered(&current, &version("1.5.0"), Some(&pinned), &[]));
+        assert!(!is_offered(&pinned, &pinned, Some(&pinned), &[]));
+    }
+
+    #[test]
+    fn test_rollout_percentage() {
+        let manifest = serde_json::json!({"version": "1.5.0", "rollout_percentage": 10});
+        assert_eq!(rollout_percentage(&manifest), Some(10));
+        assert!(is_rolled_out(9, rollout_percentage(&manifest)));
+        assert!(!is_rolled_out(10, rollout_percentage(&manifest)));
+        assert!(is_rolled_out(
+            99,
+            rollout_percentage(&serde_json::json!({}))
+        ));
+        assert!(!is_rolled_out(0, Some(0)));
+    }
+
+    #[test]
+    fn test_previous_artifact() {
+        let artifact = |version: &str| UpdateArtifact {
+            version: version.to_string(),
+            file_name: format!("{}-JustDrink.app.tar.gz", version),
+            installed_at: Utc::now(),
+            signature: None,
+        };
+        let artifacts = vec![artifact("1.3.0"), artifact("1.4.0")];
+
+        assert_eq!(
+            previous_artifact(&artifacts, &version("1.4.0")).map(|artifact| artifact.version),
+            Some("1.3.0".to_string())
+        );
+        // after a rollback, the newer installer is no rollback target
+        assert!(previous_artifact(&artifacts, &version("1.3.0")).is_none());
+    }
+
+    #[test]
+    fn test_verify_installer() {
+        let public_key = include_str!("../tests/fixtures/updater/updater-test.pub");
+        let installer = include_bytes!("../tests/fixtures/updater/installer.bin");
+        let signature = include_str!("../tests/fixtures/updater/installer.bin.sig");
+
+        assert!(verify_installer(installer, signature, public_key).is_ok());
+        let mut modified = installer.to_vec();
+        modified[0] ^= 1;
+        assert!(verify_installer(&modified, signature, public_key).is_err());
+    }
+
+    #[test]
+    fn test_install_ready() {
+        let dir = tempfile::tempdir().unwrap();
+        let persistence = Arc::new(Persistence::with_dir(dir.path().to_path_buf()).unwrap());
+        let manager = UpdateManager {
+            artifact_dir: persistence.dir().join(ARTIFACT_DIR),
+            persistence,
+            rollout_bucket: 0,
+            pending: Mutex::new(None),
+            ready: std::sync::Mutex::new(None),
+        };
+        assert_eq!(
+            manager.install_ready(false).unwrap(),
+            ReadyInstall::NotDownloaded
+        );
+
+        let installed = Arc::new(AtomicBool::new(false));
+        *manager.lock_ready() = Some(ReadyUpdate {
+            update: Box::new(FakeUpdate {
+                version: "1.5.0".to_string(),
+                installed: installed.clone(),
+            }),
+            bytes: vec![1, 2, 3],
+        });
+        assert_eq!(manager.ready_version(), Some("1.5.0".to_string()));
+
+        // a showing session keeps the update waiting
+        assert_eq!(
+            manager.install_ready(true).unwrap(),
+            ReadyInstall::Waiting("1.5.0".to_string())
+        );
+        assert!(!installed.load(Ordering::SeqCst));
+        assert_eq!(manager.ready_version(), Some("1.5.0".to_string()));
+
+        assert_eq!(
+            manager.install_ready(false).unwrap(),
+            ReadyInstall::Installed("1.5.0".to_string())
+        );
+        assert!(installed.load(Ordering::SeqCst));
+        assert_eq!(manager.ready_version(), None);
+    }
+}
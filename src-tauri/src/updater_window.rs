@@ -1,10 +1,12 @@
 use crate::alert::Alert;
-use crate::{SettingsManagerState, SettingsSystemState};
+use crate::error_catalog::ErrorCode;
+use crate::update_manager::{ReadyInstall, UpdateInfo};
+use crate::{session_window, SettingsManagerState, SettingsSystemState, UpdateManagerState};
 use anyhow::Error;
-use log::debug;
-use tauri::http::{HeaderMap, HeaderValue};
-use tauri::{AppHandle, Manager, Runtime, Window};
-use tauri_plugin_updater::UpdaterExt;
+use chrono::Utc;
+use log::{debug, info, warn};
+use tauri::{AppHandle, Manager, Runtime, State, Window};
+use tauri_plugin_notification::NotificationExt;
 
 const WINDOW_LABEL: &str = "updater";
 
@@ -22,12 +24,7 @@ pub async fn show_if_update_available(
                 shown
             }
             Err(err) => {
-                app_handle.alert(
-                    "Can't show update dialog",
-                    "Error while show update dialog.",
-                    Some(err),
-                    silent_error,
-                );
+                app_handle.alert(ErrorCode::UpdaterUnavailable, Some(err), silent_error);
                 false
             }
         }
@@ -35,22 +32,30 @@ pub async fn show_if_update_available(
     join.await.expect("updater should be able to join thread")
 }
 
-async fn show_if_update_available_run<R>(
-    app_handle: &AppHandle<R>,
+async fn show_if_update_available_run(
+    app_handle: &AppHandle,
     skip_duration_check: bool,
-) -> Result<bool, anyhow::Error>
-where
-    R: Runtime,
-{
+) -> Result<bool, anyhow::Error> {
     let shown = if !cfg!(feature = "fullversion")
         && check_for_new_version_required(app_handle.app_handle(), skip_duration_check)?
     {
-        if is_new_version_available(app_handle.app_handle()).await? {
-            debug!("found new version. show update dialog.");
-            let _ = show(&app_handle)?;
-            true
-        } else {
-            false
+        let update_manager = app_handle.state::<UpdateManagerState>();
+        match update_manager.check(app_handle).await? {
+            Some(version) if is_silent(app_handle) => {
+                debug!("found new version {version}, download it in the background.");
+                download_in_background(app_handle);
+                false
+            }
+            Some(version) if is_deferred(app_handle, &version)? => {
+                debug!("found new version {version}, the user wants to be reminded later.");
+                false
+            }
+            Some(_) => {
+                debug!("found new version. show update dialog.");
+                show(app_handle)?;
+                true
+            }
+            None => false,
         }
     } else {
         false
@@ -59,6 +64,93 @@ where
     Ok(shown)
 }
 
+fn is_silent(app_handle: &AppHandle) -> bool {
+    app_handle
+        .state::<SettingsManagerState>()
+        .get_settings()
+        .is_some_and(|settings| settings.user.silent_updates)
+}
+
+fn download_in_background(app_handle: &AppHandle) {
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        let update_manager = app_handle.state::<UpdateManagerState>();
+        if let Err(err) = update_manager.download(&app_handle).await {
+            app_handle.alert(ErrorCode::UpdateFailed, Some(err), true);
+        }
+    });
+}
+
+/// Installs the update downloaded in the background, unless a session is
+/// showing. Returns whether it was installed.
+pub fn install_downloaded(app_handle: &AppHandle) -> bool {
+    let session_visible = session_window::is_visible(app_handle);
+    match app_handle
+        .state::<UpdateManagerState>()
+        .install_ready(session_visible)
+    {
+        Ok(installed) => matches!(installed, ReadyInstall::Installed(_)),
+        Err(err) => {
+            app_handle.alert(ErrorCode::UpdateFailed, Some(err), true);
+            false
+        }
+    }
+}
+
+/// Installs the downloaded update and restarts, when the user asks for it.
+/// While a session is showing, the user is told that the update waits.
+/// Returns false, if nothing was downloaded.
+pub fn install_downloaded_on_request(app_handle: &AppHandle) -> bool {
+    let session_visible = session_window::is_visible(app_handle);
+    match app_handle
+        .state::<UpdateManagerState>()
+        .install_ready(session_visible)
+    {
+        Ok(ReadyInstall::Installed(version)) => {
+            info!("restart after installing version {version}");
+            app_handle.restart();
+        }
+        Ok(ReadyInstall::Waiting(version)) => {
+            notify(
+                app_handle,
+                &format!("Version {version} is installed after your current drink session."),
+            );
+            true
+        }
+        Ok(ReadyInstall::NotDownloaded) => false,
+        Err(err) => {
+            app_handle.alert(ErrorCode::UpdateFailed, Some(err), false);
+            true
+        }
+    }
+}
+
+fn notify(app_handle: &AppHandle, message: &str) {
+    app_handle
+        .notification()
+        .builder()
+        .title("Just Drink!")
+        .body(message)
+        .show()
+        .unwrap_or_else(|err| warn!("unable to show notification: {:?}", err));
+}
+
+/// Installs the downloaded update and restarts, e.g. while the user is idle.
+pub fn install_downloaded_and_restart(app_handle: &AppHandle) {
+    if install_downloaded(app_handle) {
+        info!("restart after installing the downloaded update");
+        app_handle.restart();
+    }
+}
+
+fn is_deferred(app_handle: &AppHandle, version: &str) -> Result<bool, Error> {
+    let settings_system = app_handle.state::<SettingsSystemState>();
+    let settings_system = settings_system
+        .lock()
+        .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+    Ok(settings_system.is_update_deferred(version))
+}
+
 fn check_for_new_version_required<R: Runtime>(
     app_handle: &AppHandle<R>,
     skip_duration_check: bool,
@@ -82,7 +174,7 @@ fn check_for_new_version_required<R: Runtime>(
     };
 
     if check_for_updates {
-        settings_system.set_last_check_date(app_handle)?;
+        settings_system.set_last_check_date()?;
     }
 
     Ok(check_for_updates)
@@ -97,16 +189,10 @@ where
         .map(|w| w.is_visible().unwrap_or(false))
         .unwrap_or(false);
     if !visible {
-        let pre_release = app_handle
-            .state::<SettingsManagerState>()
-            .get_settings()
-            .map(|s| s.user.beta_version)
-            .unwrap_or(false);
-
         let _w = tauri::WebviewWindowBuilder::new(
             app_handle,
             WINDOW_LABEL,
-            tauri::WebviewUrl::App(format!("/updater?prerelease={:?}", pre_release).into()),
+            tauri::WebviewUrl::App("/updater".into()),
         )
         .title("New version is available")
         .resizable(false)
@@ -124,35 +210,86 @@ where
     Ok(())
 }
 
-async fn is_new_version_available<R>(app: &AppHandle<R>) -> Result<bool, anyhow::Error>
-where
-    R: Runtime,
-{
-    let pre_release: bool = app
-        .state::<SettingsManagerState>()
-        .get_settings()
-        .map(|s| s.user.beta_version)
-        .unwrap_or(false);
+/// The channel and the offered version. Checks for an update, if the window
+/// was opened without one.
+#[specta::specta]
+#[tauri::command]
+pub async fn updater_info(
+    app: AppHandle,
+    update_manager: State<'_, UpdateManagerState>,
+) -> Result<UpdateInfo, String> {
+    let info = update_manager.info(&app).await;
+    if info.version.is_some() {
+        return Ok(info);
+    }
+    update_manager
+        .check(&app)
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(update_manager.info(&app).await)
+}
 
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        "prerelease",
-        HeaderValue::from_str(pre_release.to_string().as_str())?,
-    );
+/// Downloads and installs the offered version, the app has to be relaunched
+/// afterwards.
+#[specta::specta]
+#[tauri::command]
+pub async fn updater_install(
+    app: AppHandle,
+    update_manager: State<'_, UpdateManagerState>,
+) -> Result<(), String> {
+    update_manager
+        .install(&app)
+        .await
+        .map_err(|err| err.to_string())
+}
 
-    debug!("checking for new update, pre-release: {pre_release}");
+/// The version isn't offered again.
+#[specta::specta]
+#[tauri::command]
+pub fn updater_skip_version(
+    window: Window,
+    version: String,
+    settings_system: State<SettingsSystemState>,
+) {
+    debug!("skip version {version}");
+    settings_system
+        .lock()
+        .expect("settings_system lock required")
+        .skip_version(version);
+    window.destroy().expect("updater should be destroyed");
+}
 
-    if let Some(_update) = app
-        .updater_builder()
-        .headers(headers)
-        .build()?
-        .check()
-        .await?
-    {
-        Ok(true)
-    } else {
-        Ok(false)
-    }
+/// The dialog isn't shown for this version, until the given number of days
+/// passed.
+#[specta::specta]
+#[tauri::command]
+pub fn updater_remind_later(
+    window: Window,
+    version: String,
+    days: u32,
+    settings_system: State<SettingsSystemState>,
+) {
+    let until = Utc::now() + chrono::Duration::days(days as i64);
+    debug!("remind of version {version} after {until}");
+    settings_system
+        .lock()
+        .expect("settings_system lock required")
+        .defer_update(version, until);
+    window.destroy().expect("updater should be destroyed");
+}
+
+/// Installs the previous version again and returns it, the app has to be
+/// relaunched afterwards.
+#[specta::specta]
+#[tauri::command]
+pub async fn updater_rollback(
+    app: AppHandle,
+    update_manager: State<'_, UpdateManagerState>,
+) -> Result<String, String> {
+    update_manager
+        .rollback(&app)
+        .await
+        .map_err(|err| err.to_string())
 }
 
 #[specta::specta]
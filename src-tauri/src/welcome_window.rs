@@ -1,13 +1,15 @@
 use crate::alert::Alert;
 use crate::app_config::AppConfig;
+use crate::error_catalog::ErrorCode;
 use crate::model::device::DeviceId;
 use crate::model::settings::{SettingsUserDetails, WelcomeWizardMode};
 use crate::model::welcome::{WelcomeLoadSettings, WelcomeUserSettings};
 use crate::settings_manager::{SettingsManager, UserSettingsStore};
 use crate::tracking::Event;
 use crate::{
-    dashboard_window, tracking, tray, welcome_window, CountdownTimerState, LicenseManagerState,
-    SettingsManagerState, SubscriptionManagerState, TrackingState,
+    dashboard_window, tracking, tray, welcome_window, AutostartState, CheckoutWatcherState,
+    CountdownTimerState, LicenseManagerState, SettingsManagerState, SubscriptionManagerState,
+    TrackingState,
 };
 use anyhow::anyhow;
 use log::{info, warn};
@@ -57,15 +59,17 @@ pub async fn show(
     .build()?;
 
     let event = match welcome_mode {
-        WelcomeWizardMode::Complete => tracking::Event::Install,
-        WelcomeWizardMode::OnlySipSettings => tracking::Event::ResetSettings,
-        WelcomeWizardMode::OnlyPayment => tracking::Event::OnlyPayment,
-        WelcomeWizardMode::CancelPayment => tracking::Event::CancelPayment,
+        // the install is tracked, once the user answered the consent question
+        WelcomeWizardMode::Complete => None,
+        WelcomeWizardMode::OnlySipSettings => Some(tracking::Event::ResetSettings),
+        WelcomeWizardMode::OnlyPayment => Some(tracking::Event::OnlyPayment),
+        WelcomeWizardMode::CancelPayment => Some(tracking::Event::CancelPayment),
+        WelcomeWizardMode::OnlyConsent => None,
     };
 
-    app.state::<TrackingState>()
-        .send_tracking(event)
-        .await;
+    if let Some(event) = event {
+        app.state::<TrackingState>().send_tracking(event).await;
+    }
 
     Ok(())
 }
@@ -109,12 +113,7 @@ pub async fn welcome_redo(
     )
     .await
     .unwrap_or_else(|err| {
-        app.alert(
-            "Unable to reset",
-            "I am sorry, we are unable to reset the settings. Please try again later.",
-            Some(err),
-            false,
-        )
+        app.alert(ErrorCode::SettingsResetFailed, Some(err), false)
     });
     Ok(())
 }
@@ -129,6 +128,7 @@ pub async fn welcome_save(
     settings_manager: State<'_, SettingsManagerState>,
     subscription_manager: State<'_, SubscriptionManagerState>,
     timer: State<'_, CountdownTimerState>,
+    autostart: State<'_, AutostartState>,
 ) -> Result<(), String> {
     tray::show_tray_icon(app.app_handle());
 
@@ -137,12 +137,11 @@ pub async fn welcome_save(
             .subscribe(email, consent)
             .await
             .unwrap_or_else(|err| {
-                app.alert(
-                    "Can't subscribe",
-                    "There was an error while subscribing",
-                    Some(err),
-                    true,
-                )
+                if err.is_retryable() {
+                    warn!("unable to subscribe, server not available: {:?}", err);
+                } else {
+                    app.alert(ErrorCode::NewsletterSubscriptionFailed, Some(err.into()), true)
+                }
             });
     }
 
@@ -162,8 +161,7 @@ pub async fn welcome_save(
         ..current_settings
     }).unwrap_or_else(|err|
         app.alert(
-            "Error while saving",
-            "I am sorry, I am unable to save your settings. Please contact Rocket Solutions for support.",
+            ErrorCode::SettingsNotSaved,
             Some(err),
             false)
     );
@@ -176,6 +174,7 @@ pub async fn welcome_save(
             timer.start(Duration::from_secs(
                 (s.user.next_break_duration_minutes * 60) as u64,
             ));
+            autostart.reconcile(&app, s.user.enable_on_startup);
         }
     }
     Ok(())
@@ -215,16 +214,20 @@ pub async fn welcome_close(
 
 #[specta::specta]
 #[tauri::command]
-pub fn open_payment(app: AppHandle, tracking: State<'_, TrackingState>) {
+pub fn open_payment(
+    app: AppHandle,
+    tracking: State<'_, TrackingState>,
+    checkout_watcher: State<'_, CheckoutWatcherState>,
+) {
     let url = format!(
         "{baseUrl}/pricing/checkout/justdrink/{deviceId}",
         baseUrl = AppConfig::build().get_url(),
         deviceId = tracking.device_id().get_hash_hex_id()
     );
     match webbrowser::open(url.as_str()) {
-        Ok(_) => {}
+        Ok(_) => checkout_watcher.watch(&app),
         Err(err) => {
-            app.alert("Unable to redirect to payment site", "Please ensure that your default browser is working. We are experience problems, opening the default browser", Some(anyhow!(err)), false);
+            app.alert(ErrorCode::PaymentPageUnavailable, Some(anyhow!(err)), false);
         }
     }
 }
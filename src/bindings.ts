@@ -5,8 +5,12 @@
 
 
 export const commands = {
-async alertLogClientError(title: string, message: string, error: string) : Promise<void> {
-    await TAURI_INVOKE("alert_log_client_error", { title, message, error });
+/**
+ * Logs errors of the UI. Free text is passed as detail, the user facing text
+ * comes from the catalog.
+ */
+async alertLogClientError(code: ErrorCode, detail: string, error: string) : Promise<void> {
+    await TAURI_INVOKE("alert_log_client_error", { code, detail, error });
 },
 async getCurrentTimerStatus() : Promise<TimerStatus> {
     return await TAURI_INVOKE("get_current_timer_status");
@@ -17,8 +21,8 @@ async toggleTimer() : Promise<void> {
 async timerChange(changeTime: ChangeTime) : Promise<void> {
     await TAURI_INVOKE("timer_change", { changeTime });
 },
-async feedbackWindowSendFeedback(feedback: string, rating: FeedbackRate) : Promise<null> {
-    return await TAURI_INVOKE("feedback_window_send_feedback", { feedback, rating });
+async feedbackWindowSendFeedback(feedback: string, rating: FeedbackRate, category: FeedbackCategory, attachDiagnostics: boolean, crashReportId: string | null) : Promise<FeedbackDelivery> {
+    return await TAURI_INVOKE("feedback_window_send_feedback", { feedback, rating, category, attachDiagnostics, crashReportId });
 },
 async isFullVersionAndMac() : Promise<boolean> {
     return await TAURI_INVOKE("is_full_version_and_mac");
@@ -26,6 +30,31 @@ async isFullVersionAndMac() : Promise<boolean> {
 async openAppStoreFeedback() : Promise<null> {
     return await TAURI_INVOKE("open_app_store_feedback");
 },
+/**
+ * Sends the 0-10 answer of the NPS survey.
+ */
+async feedbackWindowSendNps(score: number, comment: string) : Promise<null> {
+    return await TAURI_INVOKE("feedback_window_send_nps", { score, comment });
+},
+/**
+ * The prompt, which the window should show. None, if the user opened the
+ * window.
+ */
+async pendingFeedbackPrompt() : Promise<PromptKind | null> {
+    return await TAURI_INVOKE("pending_feedback_prompt");
+},
+async feedbackWindowDismissPrompt() : Promise<void> {
+    await TAURI_INVOKE("feedback_window_dismiss_prompt");
+},
+async pendingCrashReport() : Promise<CrashReportSummary | null> {
+    return await TAURI_INVOKE("pending_crash_report");
+},
+/**
+ * The user doesn't want to send the report, it isn't offered again.
+ */
+async dismissCrashReport(id: string) : Promise<null> {
+    return await TAURI_INVOKE("dismiss_crash_report", { id });
+},
 async startSession(drinkSettings: SessionStartEvent | null) : Promise<null> {
     return await TAURI_INVOKE("start_session", { drinkSettings });
 },
@@ -41,12 +70,44 @@ async loadSettings() : Promise<Settings> {
 async getDeviceId() : Promise<string> {
     return await TAURI_INVOKE("get_device_id");
 },
-async updateSettings(settings: SettingsUserDetails) : Promise<null> {
+async updateSettings(settings: SettingsUserDetails) : Promise<AutostartStatus> {
     return await TAURI_INVOKE("update_settings", { settings });
 },
 async openBrowser(url: string, close: boolean) : Promise<null> {
     return await TAURI_INVOKE("open_browser", { url, close });
 },
+async getTelemetryConsent() : Promise<TelemetryConsent> {
+    return await TAURI_INVOKE("get_telemetry_consent");
+},
+async setTelemetryConsent(consent: TelemetryConsent) : Promise<null> {
+    return await TAURI_INVOKE("set_telemetry_consent", { consent });
+},
+/**
+ * Everything we sent recently, for the "What we sent" view.
+ */
+async privacyAuditLog() : Promise<AuditEntry[]> {
+    return await TAURI_INVOKE("privacy_audit_log");
+},
+/**
+ * Sends the erasure request, unsubscribes from the newsletter and deletes all
+ * local stores and logs. Nothing is deleted, if the erasure request fails.
+ */
+async deleteMyData() : Promise<DataDeletionResult> {
+    return await TAURI_INVOKE("delete_my_data");
+},
+/**
+ * Restarts into the welcome wizard, after the data has been deleted.
+ */
+async restartAfterDataDeletion() : Promise<void> {
+    await TAURI_INVOKE("restart_after_data_deletion");
+},
+/**
+ * Collects logs, settings and the app state for a support ticket. Secrets are
+ * removed, the zip is shown in the file manager.
+ */
+async createDiagnosticsBundle() : Promise<string> {
+    return await TAURI_INVOKE("create_diagnostics_bundle");
+},
 async welcomeWith(welcomeWizardMode: WelcomeWizardMode) : Promise<void> {
     await TAURI_INVOKE("welcome_with", { welcomeWizardMode });
 },
@@ -71,8 +132,63 @@ async closeErrorWindow() : Promise<void> {
 async updaterClose() : Promise<void> {
     await TAURI_INVOKE("updater_close");
 },
+/**
+ * The channel and the offered version. Checks for an update, if the window
+ * was opened without one.
+ */
+async updaterInfo() : Promise<UpdateInfo> {
+    return await TAURI_INVOKE("updater_info");
+},
+/**
+ * Downloads and installs the offered version, the app has to be relaunched
+ * afterwards.
+ */
+async updaterInstall() : Promise<null> {
+    return await TAURI_INVOKE("updater_install");
+},
+/**
+ * The version isn't offered again.
+ */
+async updaterSkipVersion(version: string) : Promise<void> {
+    await TAURI_INVOKE("updater_skip_version", { version });
+},
+/**
+ * The dialog isn't shown for this version, until the given number of days
+ * passed.
+ */
+async updaterRemindLater(version: string, days: number) : Promise<void> {
+    await TAURI_INVOKE("updater_remind_later", { version, days });
+},
+/**
+ * Installs the previous version again and returns it, the app has to be
+ * relaunched afterwards.
+ */
+async updaterRollback() : Promise<string> {
+    return await TAURI_INVOKE("updater_rollback");
+},
 async requestLicenseStatus() : Promise<LicenseData> {
     return await TAURI_INVOKE("request_license_status");
+},
+async activateLicenseKey(licenseKey: string) : Promise<LicenseData> {
+    return await TAURI_INVOKE("activate_license_key", { licenseKey });
+},
+async deactivateLicenseKey(licenseKey: string, deviceId: string | null) : Promise<null> {
+    return await TAURI_INVOKE("deactivate_license_key", { licenseKey, deviceId });
+},
+async licenseDevices(licenseKey: string) : Promise<LicenseDevice[]> {
+    return await TAURI_INVOKE("license_devices", { licenseKey });
+},
+async cancelPayment() : Promise<null> {
+    return await TAURI_INVOKE("cancel_payment");
+},
+/**
+ * The last known license, without asking the license server.
+ */
+async currentLicenseStatus() : Promise<LicenseData | null> {
+    return await TAURI_INVOKE("current_license_status");
+},
+async redeemCode(code: string) : Promise<LicenseData> {
+    return await TAURI_INVOKE("redeem_code", { code });
 }
 }
 
@@ -81,19 +197,25 @@ async requestLicenseStatus() : Promise<LicenseData> {
 
 export const events = __makeEvents__<{
 countdownEvent: CountdownEvent,
+licenseChanged: LicenseChanged,
 licenseResult: LicenseResult,
 sessionStartEvent: SessionStartEvent,
 settings: Settings,
 settingsUserDetails: SettingsUserDetails,
 timerStatus: TimerStatus,
+updateProgress: UpdateProgress,
+updateReady: UpdateReady,
 welcomeWizardMode: WelcomeWizardMode
 }>({
 countdownEvent: "countdown-event",
+licenseChanged: "license-changed",
 licenseResult: "license-result",
 sessionStartEvent: "session-start-event",
 settings: "settings",
 settingsUserDetails: "settings-user-details",
 timerStatus: "timer-status",
+updateProgress: "update-progress",
+updateReady: "update-ready",
 welcomeWizardMode: "welcome-wizard-mode"
 })
 
@@ -103,29 +225,90 @@ welcomeWizardMode: "welcome-wizard-mode"
 
 /** user-defined types **/
 
-export type AppDetails = { version: string; device_id: string; url: string }
+export type AppDetails = { version: string; device_id: string; rollout_bucket: number; url: string; custom_backend: boolean }
+/**
+ * One payload, which left the machine.
+ */
+export type AuditEntry = { timestamp: string; method: string; destination: string; headers: Partial<{ [key in string]: string }>; body: string }
+export type AutostartStatus = { enabled: boolean; error: string | null }
 export type ChangeTime = { Add: number } | { Remove: number }
 export type CountdownEvent = { status: TimerStatus }
+/**
+ * The part of a crash report, which is shown when offering to send it.
+ */
+export type CrashReportSummary = { id: string; created_at: string; app_version: string; message: string }
+export type DataDeletionResult = { erasure: ErasureStatus; reference: string | null; newsletter_unsubscribed: boolean; remaining_files: string[] }
 export type DrinkCharacter = "YoungWoman" | "YoungMan"
+/**
+ * Outcome of the erasure request for the data stored on our servers.
+ */
+export type ErasureStatus = "Accepted" | "NothingStored"
+/**
+ * Every error, which is shown to the user. The codes are stable, so support
+ * requests and error reports can be matched across versions.
+ */
+export type ErrorCode = "DashboardUnavailable" | "SettingsUnavailable" | "FeedbackUnavailable" | "UpdaterUnavailable" | "ActionMenuUnavailable" | "SessionWindowMissing" | "SessionStartFailed" | "TrayUpdateFailed" | "LicenseServerUnreachable" | "LicenseRejected" | "LicenseUnverified" | "SettingsNotSaved" | "SettingsResetFailed" | "NewsletterSubscriptionFailed" | "FeedbackRejected" | "ReviewUnavailable" | "BrowserUnavailable" | "PaymentPageUnavailable" | "UpdateFailed" | "RelaunchFailed" | "ClientError"
+export type FeedbackCategory = "GENERAL" | "BUG" | "IDEA" | "BILLING"
+/**
+ * Whether the feedback reached the server or waits for the connection.
+ */
+export type FeedbackDelivery = "Sent" | "Queued"
 export type FeedbackRate = "UNKNOWN" | "BAD" | "OK" | "AWESOME"
 export type GenderType = "Male" | "Female" | "Other"
+/**
+ * Sent whenever the license changed, e.g. after a background refresh.
+ */
+export type LicenseChanged = { license: LicenseData }
 export type LicenseData = { payment: LicensePaymentInfo; info: LicenseInfo }
+export type LicenseDevice = { device_id: string; activated_at: string; current: boolean }
 export type LicenseInfo = { status: LicenseInfoStatus; license_key: string | null; message: string | null }
-export type LicenseInfoStatus = "Trial" | "Paid" | "Full" | "Invalid"
-export type LicensePaymentInfo = { total_trail_days: number; trial_days_left: number; purchase_price: number; payment_status: LicensePaymentStatus }
+export type LicenseInfoStatus = "Trial" | "Paid" | "Full" | "Invalid" | "Unknown"
+export type LicenseKeyError = "InvalidLicenseKey" | "SeatLimitReached" | "DeviceNotActivated" | { Server: ServerError }
+export type LicensePaymentInfo = { total_trail_days: number; trial_days_left: number; purchase_price: Price; regular_price: Price | null; payment_status: LicensePaymentStatus }
 export type LicensePaymentStatus = "GoToCheckout" | "ReadyToCapture" | "Paid" | "Canceled" | "Error"
-export type LicenseResult = { status: LicenseResultStatus; error: string | null }
-export type LicenseResultStatus = "Success" | "Error"
+/**
+ * Progress of a checkout, which was opened in the browser.
+ */
+export type LicenseResult = { status: LicenseResultStatus; payment_status: LicensePaymentStatus | null; error: string | null }
+export type LicenseResultStatus = "Pending" | "Success" | "Canceled" | "TimedOut" | "Error"
 export type PauseOrigin = "Idle" | { PreventSleep: string } | "User"
+/**
+ * A price in the smallest unit of the currency, formatted for the user's locale.
+ */
+export type Price = { amount_minor: number; currency: string; formatted: string; tax_note: string | null }
+/**
+ * The prompts, which may be shown after a session.
+ */
+export type PromptKind = "Feedback" | "Review" | "Nps"
+export type RedeemCodeError = "Expired" | "AlreadyUsed" | "Invalid" | { Server: ServerError }
+export type ServerError = { NetworkUnavailable: string } | { Timeout: string } | { Rejected: { status: number; error: string; message: string; from_backend: boolean } } | { ServerFailure: { status: number } } | { BadPayload: string } | { Tls: string }
 export type SessionStartEvent = { selected_drink_character: DrinkCharacter; sip_size: SipSize; demo_mode: boolean }
-export type Settings = { app: AppDetails; user: SettingsUserDetails; selected_tab: SettingsTabs }
+export type Settings = { app: AppDetails; user: SettingsUserDetails; autostart: AutostartStatus; selected_tab: SettingsTabs }
 export type SettingsTabs = "Session" | "Tracking" | "License" | "About"
-export type SettingsUserDetails = { next_break_duration_minutes: number; drink_amount_ml: number; sip_size: SipSize; character: DrinkCharacter; gender_type: GenderType; consent: boolean; active: boolean; allow_tracking: boolean; enable_on_startup: boolean; beta_version: boolean; enable_idle_detection: boolean }
+export type SettingsUserDetails = { next_break_duration_minutes: number; drink_amount_ml: number; sip_size: SipSize; character: DrinkCharacter; gender_type: GenderType; consent: boolean; active: boolean; enable_on_startup: boolean; update_channel: UpdateChannel; pinned_version: string | null; silent_updates: boolean; enable_idle_detection: boolean }
 export type SipSize = "BigSip" | "HalfCup" | "FullCup"
+/**
+ * The opt-ins for data, which is sent to us without a user action.
+ */
+export type TelemetryConsent = { answered: boolean; usage_analytics: boolean; crash_reports: boolean; error_reports: boolean }
 export type TimerStatus = { NotStarted: number } | { Active: number } | { Paused: [PauseOrigin, number] } | "Finished"
+/**
+ * Where updates come from. Older versions only knew `beta_version`.
+ */
+export type UpdateChannel = "Stable" | "Beta" | "Nightly"
+/**
+ * What the updater window shows.
+ */
+export type UpdateInfo = { channel: UpdateChannel; current_version: string; version: string | null; date: string | null; notes: string | null; pinned_version: string | null; rollback_version: string | null }
+export type UpdateProgress = { Started: { content_length: number | null } } | { Progress: { chunk_length: number } } | "Finished"
+/**
+ * An update was downloaded in the background and is installed on quit or
+ * when the user is idle.
+ */
+export type UpdateReady = { version: string }
 export type WelcomeLoadSettings = { user: SettingsUserDetails | null; device_id: string; backend_url: string }
 export type WelcomeUserSettings = { next_break_duration_minutes: number; drink_amount_ml: number; sip_size: SipSize; character: DrinkCharacter; gender_type: GenderType }
-export type WelcomeWizardMode = "Complete" | "OnlySipSettings" | "OnlyPayment" | "CancelPayment"
+export type WelcomeWizardMode = "Complete" | "OnlySipSettings" | "OnlyPayment" | "CancelPayment" | "OnlyConsent"
 
 /** tauri-specta globals **/
 
@@ -5,10 +5,6 @@ import type {HandleClientError} from "@sveltejs/kit";
 
 export const handleError: HandleClientError = async ({ error, event, message }) => {
     await warn(`Client-side error: ${message}, with event: ${event.route}, ${event.url}, error: ${error}`);
-    await commands.alertLogClientError(
-        "Client error",
-        `There was an error while running UI. If the error persists, please contact me at info@rocket-solutions.de. Error: ${message}`,
-        `${error}`
-    );
+    await commands.alertLogClientError("ClientError", message, `${error}`);
     await getCurrentWindow().close()
 }
\ No newline at end of file
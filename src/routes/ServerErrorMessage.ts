@@ -0,0 +1,19 @@
+import type {ServerError} from "../bindings";
+
+export namespace ServerErrorMessage {
+    export function format(error: ServerError): string {
+        if ("NetworkUnavailable" in error) {
+            return "The server is not reachable. Please check your internet connection and try again."
+        } else if ("Timeout" in error) {
+            return "The server did not answer in time. Please try again."
+        } else if ("Rejected" in error) {
+            return error.Rejected.message
+        } else if ("ServerFailure" in error) {
+            return `The server has a problem (${error.ServerFailure.status}). Please try again later.`
+        } else if ("Tls" in error) {
+            return "A secure connection to the server could not be established."
+        } else {
+            return "The server sent an unexpected answer."
+        }
+    }
+}
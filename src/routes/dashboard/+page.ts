@@ -7,5 +7,6 @@ export const load: PageLoad = async () => {
     return {
         iconPath: await loadAppIcon(),
         timerStatus: await commands.getCurrentTimerStatus(),
+        license: await commands.currentLicenseStatus(),
     };
 };
\ No newline at end of file
@@ -6,6 +6,8 @@ import {commands} from "../../bindings";
 export const load: PageLoad = async () => {
     return {
         fullVersionAndMac: await commands.isFullVersionAndMac(),
+        crashReport: await commands.pendingCrashReport(),
+        prompt: await commands.pendingFeedbackPrompt(),
         iconPath: await loadAppIcon()
     };
 };
\ No newline at end of file
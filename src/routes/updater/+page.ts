@@ -1,22 +1,15 @@
-import {check, type Update} from "@tauri-apps/plugin-updater";
 import {loadAppIcon} from "../../app";
+import {commands, type UpdateInfo} from "../../bindings";
 import type {UpdateState} from "./updateState";
 
-function getPrereleaseHeader(): boolean {
-    const prereleaseParam = new URLSearchParams(window.location.search).get("prerelease");
-    return prereleaseParam === "true";
-}
-
 /** @type {import('./$types').PageLoad} */
 export async function load(): Promise<{ iconPath: string; updateState: UpdateState }> {
     const iconPath = await loadAppIcon();
-    const updateState = await check({
-        headers: { "prerelease": String(getPrereleaseHeader()) }
-    }).then((update: Update | null): UpdateState => {
-        if (update === null) {
-            return {state: "upToDate"};
+    const updateState = await commands.updaterInfo().then((info: UpdateInfo): UpdateState => {
+        if (info.version === null) {
+            return {state: "upToDate", info};
         } else {
-            return {state: "updateAvailable", update: update};
+            return {state: "updateAvailable", info, version: info.version};
         }
     }).catch((e) => {
         throw Error(`Error while updating: ${e}`);
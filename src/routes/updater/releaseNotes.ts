@@ -0,0 +1,88 @@
+/**
+ * Release notes are markdown from the update server. They are parsed into
+ * plain text spans and rendered by svelte, so no markup of the notes ever
+ * reaches the DOM. Only http(s) links are kept.
+ */
+export interface Span {
+    text: string,
+    bold?: boolean,
+    italic?: boolean,
+    code?: boolean,
+    href?: string
+}
+
+export type Block =
+    { kind: "heading", spans: Span[] } |
+    { kind: "paragraph", spans: Span[] } |
+    { kind: "list", items: Span[][] }
+
+const INLINE = /\*\*(.+?)\*\*|__(.+?)__|\*(.+?)\*|_(.+?)_|`(.+?)`|\[(.+?)]\((.+?)\)/g;
+
+export function parseReleaseNotes(markdown: string): Block[] {
+    const blocks: Block[] = [];
+    let paragraph: string[] = [];
+    let list: Span[][] = [];
+
+    const flush = () => {
+        if (paragraph.length > 0) {
+            blocks.push({kind: "paragraph", spans: parseInline(paragraph.join(" "))});
+            paragraph = [];
+        }
+        if (list.length > 0) {
+            blocks.push({kind: "list", items: list});
+            list = [];
+        }
+    };
+
+    for (const rawLine of markdown.split(/\r?\n/)) {
+        const line = rawLine.trim();
+        const heading = line.match(/^#{1,6}\s+(.*)$/);
+        const item = line.match(/^(?:[-*+]|\d+\.)\s+(.*)$/);
+        if (line === "") {
+            flush();
+        } else if (heading) {
+            flush();
+            blocks.push({kind: "heading", spans: parseInline(heading[1])});
+        } else if (item) {
+            if (paragraph.length > 0) {
+                flush();
+            }
+            list.push(parseInline(item[1]));
+        } else {
+            if (list.length > 0) {
+                flush();
+            }
+            paragraph.push(line);
+        }
+    }
+    flush();
+    return blocks;
+}
+
+function parseInline(text: string): Span[] {
+    const spans: Span[] = [];
+    let last = 0;
+    for (const match of text.matchAll(INLINE)) {
+        const index = match.index ?? 0;
+        if (index > last) {
+            spans.push({text: text.slice(last, index)});
+        }
+        const [, bold1, bold2, italic1, italic2, code, linkText, href] = match;
+        if (bold1 ?? bold2) {
+            spans.push({text: bold1 ?? bold2, bold: true});
+        } else if (italic1 ?? italic2) {
+            spans.push({text: italic1 ?? italic2, italic: true});
+        } else if (code) {
+            spans.push({text: code, code: true});
+        } else if (/^https?:\/\//i.test(href)) {
+            spans.push({text: linkText, href});
+        } else {
+            spans.push({text: linkText});
+        }
+        last = index + match[0].length;
+    }
+    if (last < text.length) {
+        spans.push({text: text.slice(last)});
+    }
+    return spans;
+}
\ No newline at end of file
@@ -1,12 +1,14 @@
-import type {Update} from "@tauri-apps/plugin-updater";
+import type {UpdateInfo} from "../../bindings";
 
 export interface UpToDate {
-    state: "upToDate"
+    state: "upToDate",
+    info: UpdateInfo
 }
 
 export interface UpdateAvailable {
     state: "updateAvailable",
-    update: Update
+    info: UpdateInfo,
+    version: string
 }
 
 export type UpdateState = UpToDate | UpdateAvailable
\ No newline at end of file
@@ -1,5 +1,6 @@
 export type WelcomeStep =
     "Start"
+    | "Consent"
     | "GenderType"
     | "Weight"
     | "DrinkAmount"